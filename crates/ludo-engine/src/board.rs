@@ -0,0 +1,424 @@
+use crate::Rules;
+
+pub const BASE: i8 = -1;
+pub const START: i8 = 0;
+pub const LAST_TRACK_POSITION: i8 = 50;
+pub const HOME: i8 = 56;
+
+pub const SAFE_POSITIONS: [i8; 8] = [0, 13, 26, 39, 8, 21, 34, 47];
+
+/// Everything the rules need to know about a game. Positions are relative to the
+/// color's own start square: `BASE` before entering, `START..=LAST_TRACK_POSITION`
+/// on the shared track, then the home column up to `HOME`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardState {
+    pub seated: [bool; 4],
+    pub token_positions: [[i8; 4]; 4],
+    // finishing place of each color (1 is the winner), 0 while still playing
+    pub places: [u8; 4],
+    pub cur_player: u8,
+    pub six_count: u8,
+    pub rules: Rules,
+}
+
+impl BoardState {
+    pub fn new(seated: [bool; 4], cur_player: u8, rules: Rules) -> Self {
+        Self {
+            seated,
+            token_positions: [[BASE; 4]; 4],
+            places: [0; 4],
+            cur_player,
+            six_count: 0,
+            rules,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_active(&self, color: u8) -> bool {
+        self.seated[color as usize] && self.places[color as usize] == 0
+    }
+
+    pub fn active_players(&self) -> u8 {
+        (0..4).filter(|&color| self.is_active(color)).count() as u8
+    }
+
+    /// Every seated color has a place: the last one standing is placed as soon as it is
+    /// alone, and a teams game places everyone once a side is done.
+    pub fn is_finished(&self) -> bool {
+        self.active_players() == 0
+    }
+
+    #[inline(always)]
+    pub fn partner(color: u8) -> u8 {
+        (color + 2) % 4
+    }
+
+    pub fn is_opponent(&self, color: u8, other: u8) -> bool {
+        other != color && !(self.rules.teams && other == Self::partner(color))
+    }
+
+    pub fn all_home(&self, color: u8) -> bool {
+        self.token_positions[color as usize]
+            .iter()
+            .all(|&position| position == HOME)
+    }
+
+    /// Color whose tokens the current player moves: their partner's once their own are all
+    /// home and partner assist is on.
+    pub fn moving_color(&self) -> u8 {
+        if self.rules.partner_assist && self.all_home(self.cur_player) {
+            Self::partner(self.cur_player)
+        } else {
+            self.cur_player
+        }
+    }
+
+    // a teams player done before their partner sits out unless they can assist
+    fn takes_turns(&self, color: u8) -> bool {
+        self.is_active(color)
+            && (!self.rules.teams || self.rules.partner_assist || !self.all_home(color))
+    }
+
+    pub fn next_player(&mut self) {
+        loop {
+            self.cur_player = (self.cur_player + 1) % 4;
+            if self.takes_turns(self.cur_player) {
+                break;
+            }
+        }
+    }
+
+    fn free_places(&self) -> impl DoubleEndedIterator<Item = u8> + '_ {
+        let seated = self.seated.iter().filter(|&&seated| seated).count() as u8;
+        (1..=seated).filter(|place| !self.places.contains(place))
+    }
+
+    fn best_free_place(&self) -> u8 {
+        self.free_places().next().unwrap()
+    }
+
+    fn worst_free_place(&self) -> u8 {
+        self.free_places().next_back().unwrap()
+    }
+
+    /// Once a single player is left, they take the remaining place and the game is finished.
+    fn place_last_standing(&mut self) {
+        if self.active_players() == 1 {
+            let last = (0..4).find(|&color| self.is_active(color)).unwrap();
+            self.places[last as usize] = self.best_free_place();
+        }
+    }
+
+    /// Places the side of `color` in the best free places, whoever is further along first.
+    fn place_team(&mut self, color: u8) {
+        let partner = Self::partner(color);
+        let progress = |positions: [i8; 4]| positions.iter().map(|&p| p as i16).sum::<i16>();
+        let (ahead, behind) = if progress(self.token_positions[partner as usize])
+            > progress(self.token_positions[color as usize])
+        {
+            (partner, color)
+        } else {
+            (color, partner)
+        };
+        self.places[ahead as usize] = self.best_free_place();
+        self.places[behind as usize] = self.best_free_place();
+    }
+
+    /// Gives `color`, whose tokens are all home, the best free place.
+    pub fn place_player(&mut self, color: u8) {
+        self.places[color as usize] = self.best_free_place();
+        self.place_last_standing();
+    }
+
+    /// Ends a teams game once `color` brings the last token of its side home: the partner,
+    /// who got there first, takes first place and `color` second.
+    pub fn finish_team(&mut self, color: u8) {
+        self.places[Self::partner(color) as usize] = self.best_free_place();
+        self.places[color as usize] = self.best_free_place();
+        self.place_team((color + 1) % 4);
+    }
+
+    /// Takes `color` out of the game in the worst free place, its tokens leaving the board.
+    /// In teams mode the partner goes down with them and the other side takes the top places.
+    /// Passes the dice on if it was their turn.
+    pub fn forfeit(&mut self, color: u8) {
+        let team = if self.rules.teams {
+            &[color, Self::partner(color)][..]
+        } else {
+            &[color][..]
+        };
+        for &color in team {
+            self.places[color as usize] = self.worst_free_place();
+            self.token_positions[color as usize] = [BASE; 4];
+        }
+        if self.rules.teams {
+            self.place_team((color + 1) % 4);
+        }
+        self.place_last_standing();
+        if self.cur_player == color && !self.is_finished() {
+            self.six_count = 0;
+            self.next_player();
+        }
+    }
+
+    /// Colors in finishing order, `None` for places not taken yet.
+    pub fn placements(&self) -> [Option<u8>; 4] {
+        let mut placements = [None; 4];
+        for color in 0..4 {
+            let place = self.places[color as usize];
+            if place != 0 {
+                placements[place as usize - 1] = Some(color);
+            }
+        }
+        placements
+    }
+
+    /// Square on the shared track for a token of `color`, `None` at base or in the home column.
+    pub fn track_square(color: u8, position: i8) -> Option<u8> {
+        (START..=LAST_TRACK_POSITION)
+            .contains(&position)
+            .then(|| (position as u8 + 13 * color) % 52)
+    }
+
+    pub fn opponent_blockade_at(&self, color: u8, square: u8) -> bool {
+        (0..4)
+            .filter(|&other| self.is_opponent(color, other))
+            .any(|other| {
+                self.token_positions[other as usize]
+                    .iter()
+                    .filter(|&&position| Self::track_square(other, position) == Some(square))
+                    .count()
+                    >= 2
+            })
+    }
+
+    fn path_blocked(&self, from: i8, to: i8) -> bool {
+        self.rules.blockades
+            && (from..=to.min(LAST_TRACK_POSITION)).any(|position| {
+                let color = self.moving_color();
+                let square = Self::track_square(color, position).unwrap();
+                self.opponent_blockade_at(color, square)
+            })
+    }
+
+    #[inline(always)]
+    pub fn six_limit_reached(&self, roll: u8) -> bool {
+        roll == 6
+            && self.rules.max_consecutive_sixes != 0
+            && self.six_count + 1 >= self.rules.max_consecutive_sixes
+    }
+
+    /// Where a token of the moving color at `position` ends up after `roll`,
+    /// `None` if it cannot move.
+    pub fn target_position(&self, position: i8, roll: u8) -> Option<i8> {
+        if position == BASE {
+            return (self.rules.releases(roll) && !self.path_blocked(START, START))
+                .then_some(START);
+        }
+        if position == HOME {
+            return None;
+        }
+        let new_position = position + roll as i8;
+        if self.path_blocked(position + 1, new_position) {
+            return None;
+        }
+        if new_position <= HOME {
+            Some(new_position)
+        } else if self.rules.exact_finish {
+            None
+        } else {
+            Some(2 * HOME - new_position)
+        }
+    }
+
+    /// Ends the current move: another roll after a six or a bonus, otherwise the next player.
+    pub fn finish_move(&mut self, roll: u8, bonus_turn: bool) {
+        if roll == 6 {
+            self.six_count += 1;
+        } else {
+            self.six_count = 0;
+            if !bonus_turn {
+                self.next_player();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn teams(partner_assist: bool) -> BoardState {
+        BoardState::new(
+            [true; 4],
+            0,
+            Rules {
+                teams: true,
+                partner_assist,
+                ..Rules::default()
+            },
+        )
+    }
+
+    #[test]
+    fn track_squares() {
+        assert_eq!(BoardState::track_square(0, BASE), None);
+        assert_eq!(BoardState::track_square(0, START), Some(0));
+        assert_eq!(BoardState::track_square(1, START), Some(13));
+        assert_eq!(BoardState::track_square(3, LAST_TRACK_POSITION), Some(37));
+        assert_eq!(BoardState::track_square(2, 51), None);
+    }
+
+    #[test]
+    fn next_player_skips_free_seats_and_finished_players() {
+        let mut state = BoardState::new([true, false, true, true], 0, Rules::default());
+        state.next_player();
+        assert_eq!(state.cur_player, 2);
+        state.places[3] = 1;
+        state.next_player();
+        assert_eq!(state.cur_player, 0);
+    }
+
+    #[test]
+    fn landing_on_a_single_token_captures_it() {
+        let mut state = BoardState::new([true, true, false, false], 1, Rules::default());
+        // red 5 and green 44 are the same square
+        state.token_positions[0][2] = 5;
+        state.token_positions[1][0] = 40;
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.captured, Some((0, 2)));
+        assert_eq!(outcome.bounced_by, None);
+        assert_eq!(state.token_positions[0][2], BASE);
+        assert_eq!(state.token_positions[1][0], 44);
+        assert_eq!(state.cur_player, 0);
+    }
+
+    #[test]
+    fn safe_squares_protect() {
+        let mut state = BoardState::new([true, true, false, false], 1, Rules::default());
+        // green 8 is a safe square, red 21 the same one
+        state.token_positions[0][0] = 21;
+        state.token_positions[1][0] = 4;
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.captured, None);
+        assert_eq!(state.token_positions[0][0], 21);
+
+        let mut state = BoardState::new(
+            [true, true, false, false],
+            1,
+            Rules {
+                safe_squares: false,
+                ..Rules::default()
+            },
+        );
+        state.token_positions[0][0] = 21;
+        state.token_positions[1][0] = 4;
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.captured, Some((0, 0)));
+    }
+
+    #[test]
+    fn landing_on_a_pair_bounces_back() {
+        let mut state = BoardState::new([true, true, false, false], 1, Rules::default());
+        state.token_positions[0] = [5, 5, BASE, BASE];
+        state.token_positions[1][0] = 40;
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.bounced_by, Some(0));
+        assert_eq!(outcome.captured, None);
+        assert_eq!(state.token_positions[0], [5, 5, BASE, BASE]);
+        assert_eq!(state.token_positions[1][0], BASE);
+    }
+
+    #[test]
+    fn finishing_places_players_in_order() {
+        let mut state = BoardState::new([true, true, true, false], 0, Rules::default());
+        state.token_positions[0] = [HOME, HOME, HOME, 55];
+        let outcome = apply(&mut state, 1, Action::Move(3)).unwrap();
+        assert!(outcome.player_finished && !outcome.game_finished);
+        assert_eq!(state.places, [1, 0, 0, 0]);
+        assert_eq!(state.cur_player, 1);
+
+        state.token_positions[1] = [HOME, HOME, HOME, 54];
+        let outcome = apply(&mut state, 2, Action::Move(3)).unwrap();
+        assert!(outcome.game_finished);
+        assert_eq!(state.places, [1, 2, 3, 0]);
+        assert_eq!(state.placements(), [Some(0), Some(1), Some(2), None]);
+    }
+
+    #[test]
+    fn forfeit_takes_the_worst_place() {
+        let mut state = BoardState::new([true, true, true, false], 1, Rules::default());
+        state.token_positions[1][0] = 20;
+        state.forfeit(1);
+        assert_eq!(state.places, [0, 3, 0, 0]);
+        assert_eq!(state.token_positions[1], [BASE; 4]);
+        assert_eq!(state.cur_player, 2);
+        assert!(!state.is_finished());
+
+        state.forfeit(0);
+        assert_eq!(state.places, [2, 3, 1, 0]);
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn partners_are_not_captured() {
+        let mut state = teams(false);
+        // red 5 is yellow 31
+        state.token_positions[0][0] = 1;
+        state.token_positions[2][0] = 31;
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.captured, None);
+        assert_eq!(state.token_positions[2][0], 31);
+    }
+
+    #[test]
+    fn team_places_wait_for_the_whole_side() {
+        let mut state = teams(false);
+        state.token_positions[0] = [HOME, HOME, HOME, 55];
+        let outcome = apply(&mut state, 1, Action::Move(3)).unwrap();
+        assert!(outcome.player_finished && !outcome.game_finished);
+        assert_eq!(state.places, [0; 4]);
+        // red sits out without partner assist
+        assert_eq!(state.cur_player, 1);
+        state.next_player();
+        state.next_player();
+        state.next_player();
+        assert_eq!(state.cur_player, 1);
+
+        state.cur_player = 2;
+        state.token_positions[2] = [HOME, HOME, HOME, 50];
+        state.token_positions[1][0] = 10;
+        state.token_positions[3][0] = 20;
+        let outcome = apply(&mut state, 6, Action::Move(3)).unwrap();
+        assert!(outcome.game_finished);
+        // red got home first, blue is further along than green
+        assert_eq!(state.places, [1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn forfeit_in_teams_takes_the_partner_down() {
+        let mut state = teams(false);
+        state.token_positions[2][0] = 30;
+        state.forfeit(1);
+        assert_eq!(state.places, [2, 4, 1, 3]);
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn partner_assist_moves_the_partners_tokens() {
+        let mut state = teams(true);
+        state.token_positions[0] = [HOME; 4];
+        state.token_positions[2][1] = 10;
+        assert_eq!(state.moving_color(), 2);
+        assert_eq!(
+            legal_moves(&state, 3),
+            [None, Some(Action::Move(1)), None, None]
+        );
+
+        let outcome = apply(&mut state, 3, Action::Move(1)).unwrap();
+        assert_eq!(outcome.color, 2);
+        assert_eq!(state.token_positions[2][1], 13);
+        assert_eq!(state.token_positions[0], [HOME; 4]);
+        assert_eq!(state.cur_player, 1);
+    }
+}
@@ -0,0 +1,147 @@
+//! Ludo rules without any Solana dependency, so the program, bots and UIs all play
+//! by the same code.
+#![no_std]
+
+pub mod board;
+pub mod rules;
+
+pub use board::*;
+pub use rules::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Enter(u8),
+    Move(u8),
+}
+
+impl Action {
+    #[inline(always)]
+    pub fn token(&self) -> u8 {
+        match *self {
+            Action::Enter(token) | Action::Move(token) => token,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidToken,
+    WrongMove,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outcome {
+    // color of the moved token, the partner's when assisting in teams mode
+    pub color: u8,
+    pub from: i8,
+    pub to: i8,
+    // (color, token) sent back to base by this move
+    pub captured: Option<(u8, u8)>,
+    // color of the defended pair the token landed on, sending it back to base itself
+    pub bounced_by: Option<u8>,
+    pub player_finished: bool,
+    pub game_finished: bool,
+}
+
+/// Legal action for each token the current player moves after `roll`.
+pub fn legal_moves(state: &BoardState, roll: u8) -> [Option<Action>; 4] {
+    let mut moves = [None; 4];
+    if state.six_limit_reached(roll) {
+        return moves;
+    }
+    for (token, &position) in state.token_positions[state.moving_color() as usize]
+        .iter()
+        .enumerate()
+    {
+        if state.target_position(position, roll).is_some() {
+            moves[token] = Some(if position == BASE {
+                Action::Enter(token as u8)
+            } else {
+                Action::Move(token as u8)
+            });
+        }
+    }
+    moves
+}
+
+pub fn has_legal_move(state: &BoardState, roll: u8) -> bool {
+    legal_moves(state, roll).iter().any(Option::is_some)
+}
+
+/// Passes the dice on when the roll leaves the current player without a legal move.
+pub fn skip_turn(state: &mut BoardState) {
+    state.six_count = 0;
+    state.next_player();
+}
+
+pub fn apply(state: &mut BoardState, roll: u8, action: Action) -> Result<Outcome, Error> {
+    let token = action.token() as usize;
+    if token >= 4 {
+        return Err(Error::InvalidToken);
+    }
+    if state.six_limit_reached(roll) {
+        return Err(Error::WrongMove);
+    }
+
+    let color = state.moving_color();
+    let from = state.token_positions[color as usize][token];
+    match action {
+        Action::Enter(_) if from != BASE => return Err(Error::WrongMove),
+        Action::Move(_) if from == BASE => return Err(Error::WrongMove),
+        _ => {}
+    }
+    let to = state.target_position(from, roll).ok_or(Error::WrongMove)?;
+
+    state.token_positions[color as usize][token] = to;
+    let mut outcome = Outcome {
+        color,
+        from,
+        to,
+        ..Default::default()
+    };
+
+    if state.all_home(color) {
+        if !state.rules.teams {
+            state.place_player(color);
+        } else if state.all_home(BoardState::partner(color)) {
+            state.finish_team(color);
+        }
+        outcome.player_finished = true;
+        outcome.game_finished = state.is_finished();
+        if !outcome.game_finished {
+            skip_turn(state);
+        }
+        return Ok(outcome);
+    }
+
+    let safe = state.rules.safe_squares && SAFE_POSITIONS.contains(&to);
+    if let Some(square) = BoardState::track_square(color, to).filter(|_| !safe) {
+        'outer: for defender in 0..4u8 {
+            if !state.is_opponent(color, defender) || !state.seated[defender as usize] {
+                continue;
+            }
+            let positions = state.token_positions[defender as usize];
+            for def_token in 0..4 {
+                if BoardState::track_square(defender, positions[def_token]) != Some(square) {
+                    continue;
+                }
+                // a pair defends itself and sends the attacker home instead
+                if (0..4)
+                    .any(|other| other != def_token && positions[other] == positions[def_token])
+                {
+                    state.token_positions[color as usize][token] = BASE;
+                    outcome.bounced_by = Some(defender);
+                } else {
+                    state.token_positions[defender as usize][def_token] = BASE;
+                    outcome.captured = Some((defender, def_token as u8));
+                }
+                break 'outer;
+            }
+        }
+    }
+
+    let bonus_turn = (outcome.captured.is_some() && state.rules.extra_turn_on_capture)
+        || (to == HOME && state.rules.extra_turn_on_home);
+    state.finish_move(roll, bonus_turn);
+    Ok(outcome)
+}
@@ -0,0 +1,213 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub extra_turn_on_capture: bool,
+    pub extra_turn_on_home: bool,
+    // bit n set means a roll of n brings a token into play
+    pub release_rolls: u8,
+    // the n-th six in a row ends the turn, 0 for no limit
+    pub max_consecutive_sixes: u8,
+    // when false, overshooting HOME bounces the token back
+    pub exact_finish: bool,
+    pub safe_squares: bool,
+    // two tokens of one color on a square cannot be passed or landed on by opponents
+    pub blockades: bool,
+    // 2v2 with partners sitting opposite (colors c and c + 2), 4 players only
+    pub teams: bool,
+    // a teams player with every token home moves their partner's tokens instead
+    pub partner_assist: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            extra_turn_on_capture: false,
+            extra_turn_on_home: false,
+            release_rolls: 1 << 6,
+            max_consecutive_sixes: 3,
+            exact_finish: true,
+            safe_squares: true,
+            blockades: false,
+            teams: false,
+            partner_assist: false,
+        }
+    }
+}
+
+impl Rules {
+    pub const VALID_RELEASE_ROLLS: u8 = 0b0111_1110;
+
+    pub fn is_valid(&self) -> bool {
+        self.release_rolls != 0
+            && self.release_rolls & !Self::VALID_RELEASE_ROLLS == 0
+            && (self.teams || !self.partner_assist)
+    }
+
+    #[inline(always)]
+    pub fn releases(&self, roll: u8) -> bool {
+        1u8.checked_shl(roll as u32)
+            .is_some_and(|bit| self.release_rolls & bit != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn two_players(rules: Rules) -> BoardState {
+        BoardState::new([true, false, true, false], 0, rules)
+    }
+
+    #[test]
+    fn validity() {
+        assert!(Rules::default().is_valid());
+        let rules = Rules {
+            release_rolls: 0,
+            ..Rules::default()
+        };
+        assert!(!rules.is_valid());
+        let rules = Rules {
+            release_rolls: 1 << 7,
+            ..Rules::default()
+        };
+        assert!(!rules.is_valid());
+        let rules = Rules {
+            partner_assist: true,
+            ..Rules::default()
+        };
+        assert!(!rules.is_valid());
+    }
+
+    #[test]
+    fn releases_out_of_range_rolls() {
+        let rules = Rules {
+            release_rolls: Rules::VALID_RELEASE_ROLLS,
+            ..Rules::default()
+        };
+        assert!((1..=6).all(|roll| rules.releases(roll)));
+        assert!(!rules.releases(0));
+        assert!(!rules.releases(8));
+        assert!(!rules.releases(u8::MAX));
+    }
+
+    #[test]
+    fn tokens_enter_on_a_release_roll_only() {
+        let mut state = two_players(Rules::default());
+        assert!(!has_legal_move(&state, 5));
+        assert_eq!(
+            legal_moves(&state, 6),
+            [0, 1, 2, 3].map(|token| Some(Action::Enter(token)))
+        );
+        assert_eq!(
+            apply(&mut state, 5, Action::Enter(0)),
+            Err(Error::WrongMove)
+        );
+
+        let outcome = apply(&mut state, 6, Action::Enter(0)).unwrap();
+        assert_eq!((outcome.from, outcome.to), (BASE, START));
+        assert_eq!(state.token_positions[0], [START, BASE, BASE, BASE]);
+        // a six rolls again
+        assert_eq!(state.cur_player, 0);
+        assert_eq!(state.six_count, 1);
+        assert_eq!(apply(&mut state, 6, Action::Move(1)), Err(Error::WrongMove));
+
+        let mut state = two_players(Rules {
+            release_rolls: (1 << 1) | (1 << 6),
+            ..Rules::default()
+        });
+        assert!(has_legal_move(&state, 1));
+        apply(&mut state, 1, Action::Enter(2)).unwrap();
+        assert_eq!(state.cur_player, 2);
+    }
+
+    #[test]
+    fn six_limit_ends_the_turn() {
+        let mut state = two_players(Rules::default());
+        state.token_positions[0][0] = 10;
+        apply(&mut state, 6, Action::Move(0)).unwrap();
+        apply(&mut state, 6, Action::Move(0)).unwrap();
+        assert_eq!(state.six_count, 2);
+        assert!(state.six_limit_reached(6));
+        assert!(!has_legal_move(&state, 6));
+        assert_eq!(apply(&mut state, 6, Action::Move(0)), Err(Error::WrongMove));
+        assert!(has_legal_move(&state, 5));
+
+        let mut state = two_players(Rules {
+            max_consecutive_sixes: 0,
+            ..Rules::default()
+        });
+        state.token_positions[0][0] = 0;
+        for _ in 0..5 {
+            apply(&mut state, 6, Action::Move(0)).unwrap();
+        }
+        assert_eq!(state.token_positions[0][0], 30);
+        assert_eq!(state.cur_player, 0);
+    }
+
+    #[test]
+    fn overshooting_home() {
+        let mut state = two_players(Rules::default());
+        state.token_positions[0][0] = 54;
+        assert_eq!(state.target_position(54, 2), Some(HOME));
+        assert_eq!(state.target_position(54, 3), None);
+        assert!(!has_legal_move(&state, 3));
+
+        state.rules.exact_finish = false;
+        assert_eq!(state.target_position(54, 3), Some(55));
+        let outcome = apply(&mut state, 5, Action::Move(0)).unwrap();
+        assert_eq!(outcome.to, 53);
+    }
+
+    #[test]
+    fn blockades_cannot_be_passed_or_landed_on() {
+        let mut state = BoardState::new(
+            [true, true, false, false],
+            1,
+            Rules {
+                blockades: true,
+                ..Rules::default()
+            },
+        );
+        // a red pair on square 10, which is 49 for green
+        state.token_positions[0] = [10, 10, BASE, BASE];
+        state.token_positions[1][0] = 44;
+        assert!(state.opponent_blockade_at(1, 10));
+        assert_eq!(state.target_position(44, 4), Some(48));
+        assert_eq!(state.target_position(44, 5), None);
+        assert_eq!(state.target_position(44, 6), None);
+        assert_eq!(legal_moves(&state, 6)[0], None);
+
+        // a pair on a color's start square keeps its tokens in base
+        state.token_positions[0] = [13, 13, BASE, BASE];
+        state.token_positions[1] = [BASE; 4];
+        assert!(!has_legal_move(&state, 6));
+
+        // one's own pair blocks nobody of that color
+        state.token_positions[1] = [5, 5, 3, BASE];
+        assert_eq!(state.target_position(3, 4), Some(7));
+    }
+
+    #[test]
+    fn bonus_turns() {
+        let mut state = BoardState::new(
+            [true, true, false, false],
+            1,
+            Rules {
+                extra_turn_on_capture: true,
+                extra_turn_on_home: true,
+                ..Rules::default()
+            },
+        );
+        state.token_positions[0][0] = 5;
+        state.token_positions[1] = [40, 53, BASE, BASE];
+        let outcome = apply(&mut state, 4, Action::Move(0)).unwrap();
+        assert_eq!(outcome.captured, Some((0, 0)));
+        assert_eq!(state.cur_player, 1);
+
+        apply(&mut state, 3, Action::Move(1)).unwrap();
+        assert_eq!(state.token_positions[1][1], HOME);
+        assert_eq!(state.cur_player, 1);
+
+        apply(&mut state, 2, Action::Move(0)).unwrap();
+        assert_eq!(state.cur_player, 0);
+    }
+}
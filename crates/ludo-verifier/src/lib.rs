@@ -0,0 +1,282 @@
+//! Recomputes the dice of a game from the randomness the program publishes in its
+//! `GameStarted` and `DiceRolled` events, so players can audit a game after the fact.
+
+use ephemeral_vrf_sdk::rnd::random_u8_with_range;
+use ludo_engine::{BoardState, Rules};
+use sha2::{Digest, Sha256};
+
+/// A `DiceRolled` event as recorded on chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecordedRoll {
+    pub turn: u32,
+    pub color: u8,
+    pub roll: u8,
+    pub randomness: [u8; 32],
+}
+
+/// What a hash-chain roll mixed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChainInput {
+    // `roll_dice_chain`: the rolling player's key and the secret they revealed
+    Reveal { player: [u8; 32], secret: [u8; 32] },
+    // `roll_dice_delegate`: the VRF output, recorded as the randomness of the roll
+    Vrf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    // the roll does not follow from the recorded randomness
+    Roll { turn: u32, expected: u8 },
+    // a roll is missing or out of order
+    Turn { expected: u32, recorded: u32 },
+    // the hash chain does not lead to the recorded randomness
+    Chain { turn: u32 },
+    FirstPlayer { expected: u8 },
+}
+
+pub fn roll(randomness: &[u8; 32]) -> u8 {
+    random_u8_with_range(randomness, 1, 6)
+}
+
+/// Color that gets the first roll, as `Game::start` picks it from the seats taken.
+pub fn first_player(randomness: &[u8; 32], seated: [bool; 4]) -> u8 {
+    let num_players = seated.iter().filter(|&&seated| seated).count() as u8;
+    let mut board = BoardState::new(seated, 0, Rules::default());
+    if !seated[0] {
+        board.next_player();
+    }
+    for _ in 0..random_u8_with_range(randomness, 0, num_players - 1) {
+        board.next_player();
+    }
+    board.cur_player
+}
+
+pub fn verify_start(
+    randomness: &[u8; 32],
+    seated: [bool; 4],
+    recorded: u8,
+) -> Result<(), Mismatch> {
+    let expected = first_player(randomness, seated);
+    if expected != recorded {
+        return Err(Mismatch::FirstPlayer { expected });
+    }
+    Ok(())
+}
+
+/// Checks that every roll follows from its randomness and that no roll is missing in between.
+pub fn verify_rolls(rolls: &[RecordedRoll]) -> Result<(), Mismatch> {
+    for (i, recorded) in rolls.iter().enumerate() {
+        let expected = rolls[0].turn + i as u32;
+        if recorded.turn != expected {
+            return Err(Mismatch::Turn {
+                expected,
+                recorded: recorded.turn,
+            });
+        }
+        let expected = roll(&recorded.randomness);
+        if recorded.roll != expected {
+            return Err(Mismatch::Roll {
+                turn: recorded.turn,
+                expected,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Next link of a hash-chain game, as `roll_dice_chain` extends it with a revealed secret.
+pub fn chain_step(chain: &[u8; 32], turn: u32, player: &[u8; 32], secret: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(chain)
+        .chain_update(turn.to_le_bytes())
+        .chain_update(player)
+        .chain_update(secret)
+        .finalize()
+        .into()
+}
+
+/// Next link of a hash-chain game after a VRF roll, as `callback_roll_dice` extends it.
+pub fn chain_step_vrf(chain: &[u8; 32], randomness: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(chain)
+        .chain_update(randomness)
+        .finalize()
+        .into()
+}
+
+/// Replays a hash-chain game from the randomness of its `GameStarted` event, checking
+/// each recorded roll against the chain. `inputs` pairs up with `rolls`. VRF rolls are
+/// taken as recorded, their proofs are checked against the oracle's own transactions.
+pub fn verify_chain(
+    seed: &[u8; 32],
+    rolls: &[RecordedRoll],
+    inputs: &[ChainInput],
+) -> Result<(), Mismatch> {
+    verify_rolls(rolls)?;
+    let mut chain = *seed;
+    for (turn, (recorded, input)) in rolls.iter().zip(inputs).enumerate() {
+        let turn = turn as u32;
+        if recorded.turn != turn {
+            return Err(Mismatch::Turn {
+                expected: turn,
+                recorded: recorded.turn,
+            });
+        }
+        match input {
+            ChainInput::Reveal { player, secret } => {
+                chain = chain_step(&chain, turn, player, secret);
+                if chain != recorded.randomness {
+                    return Err(Mismatch::Chain { turn });
+                }
+            }
+            ChainInput::Vrf => chain = chain_step_vrf(&chain, &recorded.randomness),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sha256_hasher::hashv;
+
+    const PLAYER: [u8; 32] = [7; 32];
+
+    fn recorded(turn: u32, randomness: [u8; 32]) -> RecordedRoll {
+        RecordedRoll {
+            turn,
+            color: 0,
+            roll: roll(&randomness),
+            randomness,
+        }
+    }
+
+    /// Chain inputs and the rolls `roll_dice_chain` and `callback_roll_dice` record for them.
+    fn chain_game(seed: &[u8; 32]) -> ([RecordedRoll; 4], [ChainInput; 4]) {
+        let inputs = [
+            ChainInput::Reveal {
+                player: PLAYER,
+                secret: [1; 32],
+            },
+            ChainInput::Vrf,
+            ChainInput::Reveal {
+                player: PLAYER,
+                secret: [2; 32],
+            },
+            ChainInput::Reveal {
+                player: [9; 32],
+                secret: [3; 32],
+            },
+        ];
+        let vrf = [42; 32];
+        let mut chain = *seed;
+        let rolls = core::array::from_fn(|turn| {
+            let rolls = (turn as u32).to_le_bytes();
+            match inputs[turn] {
+                ChainInput::Reveal { player, secret } => {
+                    chain = hashv(&[&chain, &rolls, &player, &secret]).to_bytes();
+                    recorded(turn as u32, chain)
+                }
+                ChainInput::Vrf => {
+                    chain = hashv(&[&chain, &vrf]).to_bytes();
+                    recorded(turn as u32, vrf)
+                }
+            }
+        });
+        (rolls, inputs)
+    }
+
+    #[test]
+    fn chain_step_matches_the_program() {
+        let chain = [5; 32];
+        let secret = [6; 32];
+        let turn: u32 = 0x0102_0304;
+        assert_eq!(
+            chain_step(&chain, turn, &PLAYER, &secret),
+            hashv(&[&chain, &turn.to_le_bytes(), &PLAYER, &secret]).to_bytes()
+        );
+        assert_ne!(
+            chain_step(&chain, turn, &PLAYER, &secret),
+            hashv(&[&chain, &turn.to_be_bytes(), &PLAYER, &secret]).to_bytes()
+        );
+        assert_eq!(
+            chain_step_vrf(&chain, &secret),
+            hashv(&[&chain, &secret]).to_bytes()
+        );
+    }
+
+    #[test]
+    fn verify_chain_replays_a_game() {
+        let seed = [11; 32];
+        let (rolls, inputs) = chain_game(&seed);
+        assert_eq!(verify_chain(&seed, &rolls, &inputs), Ok(()));
+
+        assert_eq!(
+            verify_chain(&[12; 32], &rolls, &inputs),
+            Err(Mismatch::Chain { turn: 0 })
+        );
+
+        let mut forged = inputs;
+        forged[2] = ChainInput::Reveal {
+            player: PLAYER,
+            secret: [4; 32],
+        };
+        assert_eq!(
+            verify_chain(&seed, &rolls, &forged),
+            Err(Mismatch::Chain { turn: 2 })
+        );
+
+        let mut forged = rolls;
+        forged[3].roll = forged[3].roll % 6 + 1;
+        assert_eq!(
+            verify_chain(&seed, &forged, &inputs),
+            Err(Mismatch::Roll {
+                turn: 3,
+                expected: rolls[3].roll,
+            })
+        );
+    }
+
+    #[test]
+    fn verify_rolls_flags_a_gap() {
+        let rolls = [
+            recorded(4, [1; 32]),
+            recorded(5, [2; 32]),
+            recorded(7, [3; 32]),
+        ];
+        assert_eq!(
+            verify_rolls(&rolls),
+            Err(Mismatch::Turn {
+                expected: 6,
+                recorded: 7,
+            })
+        );
+        assert_eq!(verify_rolls(&rolls[..2]), Ok(()));
+    }
+
+    #[test]
+    fn first_player_starts_from_the_first_seat_taken() {
+        // `Game::start` moves off an empty seat 0 before counting `rnd` seats on
+        for byte in 0..=255 {
+            let randomness = [byte; 32];
+            let seated = [false, true, true, true];
+            let rnd = random_u8_with_range(&randomness, 0, 2);
+            assert_eq!(first_player(&randomness, seated), [1, 2, 3][rnd as usize]);
+
+            let seated = [false, true, false, true];
+            let rnd = random_u8_with_range(&randomness, 0, 1);
+            assert_eq!(first_player(&randomness, seated), [1, 3][rnd as usize]);
+
+            let seated = [true, false, true, false];
+            assert_eq!(first_player(&randomness, seated), [0, 2][rnd as usize]);
+        }
+        let randomness = [0; 32];
+        let seated = [false, true, true, false];
+        let expected = first_player(&randomness, seated);
+        assert_eq!(verify_start(&randomness, seated, expected), Ok(()));
+        assert_eq!(
+            verify_start(&randomness, seated, 3 - expected),
+            Err(Mismatch::FirstPlayer { expected })
+        );
+    }
+}
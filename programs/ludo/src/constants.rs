@@ -3,6 +3,42 @@ use anchor_lang::prelude::*;
 #[constant]
 pub const GAME: &[u8] = b"ludo_game";
 
+#[constant]
+pub const CONFIG: &[u8] = b"ludo_config";
+
+#[constant]
+pub const VAULT: &[u8] = b"ludo_vault";
+
+#[constant]
+pub const PROFILE: &[u8] = b"ludo_profile";
+
+#[constant]
+pub const QUEUE: &[u8] = b"ludo_queue";
+
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+// seconds without any activity after which a game may be closed unfinished
+#[constant]
+pub const ABANDONED_GAME_TIMEOUT: i64 = 3 * 24 * 60 * 60;
+
+// seconds to wait for a VRF callback before the request may be retried
+#[constant]
+pub const RANDOMNESS_TIMEOUT: i64 = 60;
+
+// turn clock of the games a match queue creates
+#[constant]
+pub const QUEUE_TURN_TIMEOUT: i64 = 60;
+#[constant]
+pub const QUEUE_MAX_MISSED_TURNS: u8 = 3;
+
+// rating difference a bracketed queue accepts, widened for every period a player waits
+#[constant]
+pub const RATING_WINDOW: u16 = 100;
+#[constant]
+pub const RATING_WINDOW_STEP: u16 = 50;
+#[constant]
+pub const RATING_WINDOW_PERIOD: i64 = 30;
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub enum Colors {
     Red = 0,
@@ -13,4 +13,48 @@ pub enum LudoError {
     WrongGameState,
     WrongMove,
     GameNotFinished,
+    InvalidRuleSet,
+    InvalidToken,
+    InvalidPayouts,
+    EntryFeeTooLow,
+    AlreadySettled,
+    InvalidRecipient,
+    MissingTokenAccounts,
+    WrongMint,
+    Unauthorized,
+    InvalidProtocolFee,
+    TooManyOracleQueues,
+    OracleQueueNotAllowed,
+    GameNotSettled,
+    InvalidTurnTimeout,
+    TurnNotExpired,
+    TeamsNeedFourPlayers,
+    CreatorCannotLeave,
+    UnexpectedRandomness,
+    RandomnessNotExpired,
+    WrongRandomnessMode,
+    InvalidCommitment,
+    InvalidReveal,
+    InvalidProfile,
+    GameNotRecorded,
+    QueueFull,
+    AlreadyQueued,
+    NotQueued,
+    NotEnoughPlayers,
+    InviteRequired,
+    WrongInvite,
+    NotInvited,
+    CommitmentNotReady,
+    InvalidRoll,
+    InvalidAdmin,
+    CommitmentReady,
+}
+
+impl From<ludo_engine::Error> for LudoError {
+    fn from(error: ludo_engine::Error) -> Self {
+        match error {
+            ludo_engine::Error::InvalidToken => LudoError::InvalidToken,
+            ludo_engine::Error::WrongMove => LudoError::WrongMove,
+        }
+    }
 }
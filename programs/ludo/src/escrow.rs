@@ -0,0 +1,455 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+
+use crate::{Config, Game, GameState, LudoError, BPS_DENOMINATOR, GAME, VAULT};
+
+pub fn deposit<'info>(
+    from: &Signer<'info>,
+    vault: &SystemAccount<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: vault.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+pub fn withdraw<'info>(
+    game: &Game,
+    vault: &SystemAccount<'info>,
+    to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    let seed = game.seed.to_le_bytes();
+    transfer(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.clone(),
+            },
+            &[&[VAULT, &seed, &[game.vault_bump]]],
+        ),
+        amount,
+    )
+}
+
+/// Token accounts of a game staked in an SPL mint. They are optional on every
+/// instruction that moves the stake, and required once `Game::mint` is set.
+pub struct TokenEscrow<'a, 'info> {
+    pub mint: &'a InterfaceAccount<'info, Mint>,
+    // associated token account of the game PDA
+    pub vault: &'a InterfaceAccount<'info, TokenAccount>,
+    pub token_program: &'a Interface<'info, TokenInterface>,
+}
+
+impl<'a, 'info> TokenEscrow<'a, 'info> {
+    pub fn new(
+        game: &Game,
+        mint: &'a Option<InterfaceAccount<'info, Mint>>,
+        vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+        token_program: &'a Option<Interface<'info, TokenInterface>>,
+    ) -> Result<Self> {
+        let (Some(mint), Some(vault), Some(token_program)) = (mint, vault, token_program) else {
+            return err!(LudoError::MissingTokenAccounts);
+        };
+        require!(game.mint == Some(mint.key()), LudoError::WrongMint);
+        Ok(Self {
+            mint,
+            vault,
+            token_program,
+        })
+    }
+
+    pub fn deposit(
+        &self,
+        from: &InterfaceAccount<'info, TokenAccount>,
+        authority: &Signer<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: from.to_account_info(),
+                    mint: self.mint.to_account_info(),
+                    to: self.vault.to_account_info(),
+                    authority: authority.to_account_info(),
+                },
+            ),
+            amount,
+            self.mint.decimals,
+        )
+    }
+
+    pub fn withdraw(
+        &self,
+        game: &Account<'info, Game>,
+        to: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        let seed = game.seed.to_le_bytes();
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.vault.to_account_info(),
+                    mint: self.mint.to_account_info(),
+                    to: to.clone(),
+                    authority: game.to_account_info(),
+                },
+                &[&[GAME, &seed, &[game.bump]]],
+            ),
+            amount,
+            self.mint.decimals,
+        )
+    }
+
+    /// Closes the emptied vault, returning its rent to `to`.
+    pub fn close(&self, game: &Account<'info, Game>, to: &AccountInfo<'info>) -> Result<()> {
+        let seed = game.seed.to_le_bytes();
+        close_account(CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            CloseAccount {
+                account: self.vault.to_account_info(),
+                destination: to.clone(),
+                authority: game.to_account_info(),
+            },
+            &[&[GAME, &seed, &[game.bump]]],
+        ))
+    }
+}
+
+pub struct Settlement {
+    pub protocol_fee: u64,
+    // wallet and amount for 1st, 2nd and 3rd place, default wallet for unpaid places
+    pub shares: [(Pubkey, u64); 3],
+}
+
+impl Settlement {
+    /// Wallets owed a transfer in the order recipients are passed: the treasury when the
+    /// game charges a protocol fee, then the paid places in finishing order.
+    fn payees(&self, game: &Game, treasury: Pubkey) -> impl Iterator<Item = (Pubkey, u64)> + '_ {
+        let fee = (game.protocol_fee_bps != 0).then_some((treasury, self.protocol_fee));
+        fee.into_iter().chain(
+            self.shares
+                .iter()
+                .copied()
+                .filter(|(player, _)| *player != Pubkey::default()),
+        )
+    }
+
+    /// Number of recipients `pay_out` takes from the front of its slice.
+    pub fn payee_count(&self, game: &Game) -> usize {
+        self.payees(game, Pubkey::default()).count()
+    }
+
+    pub fn amounts(&self) -> [u64; 3] {
+        self.shares.map(|(_, amount)| amount)
+    }
+}
+
+/// Takes the protocol fee off `pot` and splits the rest between the placed players by
+/// `Game::payouts`, any rounding dust going to the last paid place.
+pub fn split_pot(game: &Game, pot: u64) -> Result<Settlement> {
+    require!(
+        game.game_state == GameState::Finished,
+        LudoError::GameNotFinished
+    );
+    require!(!game.settled, LudoError::AlreadySettled);
+
+    let protocol_fee =
+        (pot as u128 * game.protocol_fee_bps as u128 / BPS_DENOMINATOR as u128) as u64;
+    let pot = pot - protocol_fee;
+
+    let placements = game.board().placements();
+    let paid_places = (0..3).filter(|&place| game.payouts[place] != 0);
+    let last_paid = paid_places.clone().next_back();
+
+    let mut shares = [(Pubkey::default(), 0); 3];
+    let mut remaining = pot;
+    for place in paid_places {
+        let color = placements[place].ok_or(LudoError::GameNotFinished)?;
+        let amount = if Some(place) == last_paid {
+            remaining
+        } else {
+            (pot as u128 * game.payouts[place] as u128 / BPS_DENOMINATOR as u128) as u64
+        };
+        shares[place] = (game.players[color as usize], amount);
+        remaining -= amount;
+    }
+    Ok(Settlement {
+        protocol_fee,
+        shares,
+    })
+}
+
+/// What each seated player gets back when a game is dropped: their entry fee, any
+/// difference in the vault going to the last of them. In color order.
+pub fn refunds(game: &Game, pot: u64) -> impl Iterator<Item = (Pubkey, u64)> + '_ {
+    let seated = game
+        .players
+        .iter()
+        .copied()
+        .filter(|player| *player != Pubkey::default());
+    let last = seated.clone().count().saturating_sub(1);
+    seated.enumerate().map(move |(n, player)| {
+        let amount = if n == last {
+            pot.saturating_sub(game.entry_fee * n as u64)
+        } else {
+            game.entry_fee
+                .min(pot.saturating_sub(game.entry_fee * n as u64))
+        };
+        (player, amount)
+    })
+}
+
+fn send_lamports<'info>(
+    game: &Game,
+    vault: &SystemAccount<'info>,
+    payees: impl Iterator<Item = (Pubkey, u64)>,
+    recipients: &[AccountInfo<'info>],
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let mut recipients = recipients.iter();
+    for (wallet, amount) in payees {
+        let recipient = recipients.next().ok_or(LudoError::InvalidRecipient)?;
+        require_keys_eq!(recipient.key(), wallet, LudoError::InvalidRecipient);
+        withdraw(game, vault, recipient, system_program, amount)?;
+    }
+    Ok(())
+}
+
+fn send_tokens<'info>(
+    game: &Account<'info, Game>,
+    escrow: &TokenEscrow<'_, 'info>,
+    payees: impl Iterator<Item = (Pubkey, u64)>,
+    recipients: &[AccountInfo<'info>],
+) -> Result<()> {
+    let mut recipients = recipients.iter();
+    for (wallet, amount) in payees {
+        let recipient = recipients.next().ok_or(LudoError::InvalidRecipient)?;
+        require_keys_eq!(
+            *recipient.owner,
+            escrow.token_program.key(),
+            LudoError::InvalidRecipient
+        );
+        let token_account = TokenAccount::try_deserialize(&mut &recipient.try_borrow_data()?[..])?;
+        require!(
+            token_account.owner == wallet && token_account.mint == escrow.mint.key(),
+            LudoError::InvalidRecipient
+        );
+        escrow.withdraw(game, recipient, amount)?;
+    }
+    Ok(())
+}
+
+/// Pays the vault out to `recipients`, see `Settlement::payees` for their order.
+pub fn pay_out<'info>(
+    game: &mut Game,
+    config: &Config,
+    vault: &SystemAccount<'info>,
+    recipients: &[AccountInfo<'info>],
+    system_program: &Program<'info, System>,
+) -> Result<Settlement> {
+    let settlement = split_pot(game, vault.lamports())?;
+    send_lamports(
+        game,
+        vault,
+        settlement.payees(game, config.treasury),
+        recipients,
+        system_program,
+    )?;
+    game.settled = true;
+    Ok(settlement)
+}
+
+/// Same as `pay_out` for token games, `recipients` being token accounts of the payees.
+pub fn pay_out_tokens<'info>(
+    game: &mut Account<'info, Game>,
+    config: &Config,
+    escrow: &TokenEscrow<'_, 'info>,
+    recipients: &[AccountInfo<'info>],
+) -> Result<Settlement> {
+    let settlement = split_pot(game, escrow.vault.amount)?;
+    send_tokens(
+        game,
+        escrow,
+        settlement.payees(game, config.treasury),
+        recipients,
+    )?;
+    game.settled = true;
+    Ok(settlement)
+}
+
+/// Hands every deposit back to `recipients`, the seated players' wallets in color order.
+pub fn refund_all<'info>(
+    game: &mut Game,
+    vault: &SystemAccount<'info>,
+    recipients: &[AccountInfo<'info>],
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    send_lamports(
+        game,
+        vault,
+        refunds(game, vault.lamports()),
+        recipients,
+        system_program,
+    )?;
+    game.settled = true;
+    Ok(())
+}
+
+/// Same as `refund_all` for token games, `recipients` being token accounts.
+pub fn refund_all_tokens<'info>(
+    game: &mut Account<'info, Game>,
+    escrow: &TokenEscrow<'_, 'info>,
+    recipients: &[AccountInfo<'info>],
+) -> Result<()> {
+    send_tokens(game, escrow, refunds(game, escrow.vault.amount), recipients)?;
+    game.settled = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::game::tests::{settings, started_game};
+    use crate::{GameSettings, RandomnessMode};
+
+    /// A finished game staking 1000 a seat, `places` given in color order.
+    fn finished_game(places: [u8; 4], payouts: [u16; 3], protocol_fee_bps: u16) -> Game {
+        let num_players = places.iter().filter(|&&place| place != 0).count().max(2) as u8;
+        let mut game = started_game(GameSettings {
+            entry_fee: 1000,
+            payouts,
+            protocol_fee_bps,
+            ..settings(num_players, RandomnessMode::Vrf)
+        });
+        game.places = places;
+        game.game_state = GameState::Finished;
+        game
+    }
+
+    #[test]
+    fn protocol_fee_comes_off_the_pot() {
+        let game = finished_game([2, 1, 3, 4], [BPS_DENOMINATOR, 0, 0], 250);
+        let settlement = split_pot(&game, 4000).unwrap();
+        assert_eq!(settlement.protocol_fee, 100);
+        assert_eq!(settlement.shares[0], (game.players[1], 3900));
+        assert_eq!(settlement.amounts(), [3900, 0, 0]);
+        // the treasury and the winner
+        assert_eq!(settlement.payee_count(&game), 2);
+    }
+
+    #[test]
+    fn rounding_dust_goes_to_the_last_paid_place() {
+        let game = finished_game([1, 2, 3, 4], [5000, 3000, 2000], 0);
+        let settlement = split_pot(&game, 1001).unwrap();
+        assert_eq!(settlement.protocol_fee, 0);
+        assert_eq!(settlement.amounts(), [500, 300, 201]);
+        assert_eq!(settlement.payee_count(&game), 3);
+
+        let game = finished_game([1, 2, 3, 4], [5000, 3000, 2000], 333);
+        let settlement = split_pot(&game, 4000).unwrap();
+        assert_eq!(settlement.protocol_fee, 133);
+        assert_eq!(settlement.amounts(), [1933, 1160, 774]);
+        assert_eq!(
+            settlement.protocol_fee + settlement.amounts().iter().sum::<u64>(),
+            4000
+        );
+    }
+
+    #[test]
+    fn unpaid_place_in_the_middle() {
+        let game = finished_game([3, 1, 2, 0], [6000, 0, 4000], 0);
+        let settlement = split_pot(&game, 3000).unwrap();
+        assert_eq!(
+            settlement.shares,
+            [
+                (game.players[1], 1800),
+                (Pubkey::default(), 0),
+                (game.players[0], 1200),
+            ]
+        );
+        assert_eq!(settlement.payee_count(&game), 2);
+    }
+
+    #[test]
+    fn paid_places_have_to_be_taken() {
+        let mut game = finished_game([1, 2, 3, 4], [5000, 5000, 0], 0);
+        game.places = [1, 0, 0, 0];
+        assert_eq!(
+            split_pot(&game, 4000).err(),
+            Some(LudoError::GameNotFinished.into())
+        );
+
+        let mut game = finished_game([1, 2, 3, 4], [BPS_DENOMINATOR, 0, 0], 0);
+        game.game_state = GameState::Move;
+        assert_eq!(
+            split_pot(&game, 4000).err(),
+            Some(LudoError::GameNotFinished.into())
+        );
+        game.game_state = GameState::Finished;
+        game.settled = true;
+        assert_eq!(
+            split_pot(&game, 4000).err(),
+            Some(LudoError::AlreadySettled.into())
+        );
+    }
+
+    #[test]
+    fn refunds_follow_the_vault() {
+        let mut game = started_game(GameSettings {
+            entry_fee: 100,
+            ..settings(4, RandomnessMode::Vrf)
+        });
+        game.players[1] = Pubkey::default();
+        let seated = [game.players[0], game.players[2], game.players[3]];
+        let amounts = |pot| {
+            let refunds: Vec<(Pubkey, u64)> = refunds(&game, pot).collect();
+            assert_eq!(
+                refunds
+                    .iter()
+                    .map(|(player, _)| *player)
+                    .collect::<Vec<_>>(),
+                seated
+            );
+            refunds
+                .iter()
+                .map(|(_, amount)| *amount)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(amounts(300), [100, 100, 100]);
+        // a surplus, e.g. lamports sent to the vault, goes to the last player
+        assert_eq!(amounts(350), [100, 100, 150]);
+        // a shortfall hits the last players first
+        assert_eq!(amounts(250), [100, 100, 50]);
+        assert_eq!(amounts(150), [100, 50, 0]);
+        assert_eq!(amounts(0), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,245 @@
+use anchor_lang::prelude::*;
+
+use crate::{LobbyAccess, RandomnessMode, RuleSet};
+
+/// Emits an event through `emit!`, or through a self-CPI that survives log truncation
+/// when built with the `event-cpi` feature. The accounts struct needs `#[event_cpi]`
+/// in that case.
+macro_rules! emit_event {
+    ($accounts:expr, $event:expr) => {{
+        #[cfg(feature = "event-cpi")]
+        $crate::events::emit_cpi(&$accounts.event_authority, $event)?;
+        #[cfg(not(feature = "event-cpi"))]
+        anchor_lang::prelude::emit!($event);
+    }};
+}
+
+pub(crate) use emit_event;
+
+#[cfg(feature = "event-cpi")]
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+// bump of the event authority PDA, fixed to spare a `find_program_address` on every event
+#[cfg(feature = "event-cpi")]
+pub const EVENT_AUTHORITY_BUMP: u8 = 250;
+
+#[cfg(feature = "event-cpi")]
+pub fn event_authority() -> (Pubkey, u8) {
+    let key = Pubkey::create_program_address(
+        &[EVENT_AUTHORITY_SEED, &[EVENT_AUTHORITY_BUMP]],
+        &crate::ID,
+    )
+    .unwrap();
+    (key, EVENT_AUTHORITY_BUMP)
+}
+
+#[cfg(feature = "event-cpi")]
+pub fn emit_cpi<E: anchor_lang::Event>(authority: &AccountInfo, event: E) -> Result<()> {
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use anchor_lang::solana_program::program::invoke_signed;
+
+    let data = [anchor_lang::event::EVENT_IX_TAG_LE, &event.data()].concat();
+    let ix = Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![AccountMeta::new_readonly(authority.key(), true)],
+    );
+    invoke_signed(
+        &ix,
+        std::slice::from_ref(authority),
+        &[&[EVENT_AUTHORITY_SEED, &[EVENT_AUTHORITY_BUMP]]],
+    )?;
+    Ok(())
+}
+
+#[event]
+pub struct GameCreated {
+    pub game: Pubkey,
+    pub seed: u64,
+    pub creator: Pubkey,
+    pub color: u8,
+    pub num_players: u8,
+    pub rules: RuleSet,
+    pub randomness: RandomnessMode,
+    pub access: LobbyAccess,
+}
+
+#[event]
+pub struct PlayerJoined {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub color: u8,
+}
+
+#[event]
+pub struct PlayerLeft {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub color: u8,
+}
+
+#[event]
+pub struct GameStarted {
+    pub game: Pubkey,
+    pub first_player: u8,
+    // the VRF output or commit-reveal mix the first player was drawn from
+    pub randomness: [u8; 32],
+}
+
+#[event]
+pub struct DiceRolled {
+    pub game: Pubkey,
+    pub color: u8,
+    pub roll: u8,
+    // rolls landed before this one
+    pub turn: u32,
+    // the VRF output, or the commit-reveal mix or hash chain, the roll was drawn from
+    pub randomness: [u8; 32],
+}
+
+#[event]
+pub struct DiceCommitted {
+    pub game: Pubkey,
+    pub color: u8,
+}
+
+#[event]
+pub struct DiceRevealed {
+    pub game: Pubkey,
+    pub color: u8,
+    pub secret: [u8; 32],
+}
+
+#[event]
+pub struct RandomnessRetried {
+    pub game: Pubkey,
+    pub request: Pubkey,
+}
+
+#[event]
+pub struct TurnSkipped {
+    pub game: Pubkey,
+    pub color: u8,
+    pub roll: u8,
+}
+
+#[event]
+pub struct TokenEntered {
+    pub game: Pubkey,
+    pub color: u8,
+    pub token: u8,
+}
+
+#[event]
+pub struct TokenMoved {
+    pub game: Pubkey,
+    pub color: u8,
+    pub token: u8,
+    pub from: i8,
+    pub to: i8,
+}
+
+#[event]
+pub struct TokenCaptured {
+    pub game: Pubkey,
+    pub color: u8,
+    pub token: u8,
+    pub captured_color: u8,
+    pub captured_token: u8,
+}
+
+#[event]
+pub struct CaptureBounced {
+    pub game: Pubkey,
+    pub color: u8,
+    pub token: u8,
+    pub defender: u8,
+}
+
+#[event]
+pub struct PlayerFinished {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub color: u8,
+    pub place: u8,
+}
+
+#[event]
+pub struct TurnTimedOut {
+    pub game: Pubkey,
+    pub color: u8,
+    pub missed_turns: u8,
+}
+
+#[event]
+pub struct PlayerForfeited {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub color: u8,
+    pub place: u8,
+}
+
+#[event]
+pub struct PlayerResigned {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub color: u8,
+    pub place: u8,
+}
+
+#[event]
+pub struct PlayerQueued {
+    pub queue: Pubkey,
+    pub player: Pubkey,
+}
+
+#[event]
+pub struct PlayerDequeued {
+    pub queue: Pubkey,
+    pub player: Pubkey,
+}
+
+#[event]
+pub struct GameMatched {
+    pub game: Pubkey,
+    pub queue: Pubkey,
+    pub players: [Pubkey; 4],
+}
+
+#[event]
+pub struct GameFinished {
+    pub game: Pubkey,
+    pub places: [u8; 4],
+}
+
+#[event]
+pub struct GameClosed {
+    pub game: Pubkey,
+    pub seed: u64,
+    pub creator: Pubkey,
+    pub players: [Pubkey; 4],
+    pub places: [u8; 4],
+    // closed unfinished after ABANDONED_GAME_TIMEOUT, deposits refunded
+    pub abandoned: bool,
+}
+
+#[event]
+pub struct GameSettled {
+    pub game: Pubkey,
+    pub mint: Option<Pubkey>,
+    pub protocol_fee: u64,
+    // amount paid to 1st, 2nd and 3rd place
+    pub amounts: [u64; 3],
+}
+
+#[cfg(all(test, feature = "event-cpi"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_authority_bump() {
+        let (key, bump) = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &crate::ID);
+        assert_eq!(bump, EVENT_AUTHORITY_BUMP);
+        assert_eq!(event_authority().0, key);
+    }
+}
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::{Colors, Game, GameState, LudoError, GAME};
+use crate::{withdraw, Colors, Game, GameState, LudoError, TokenEscrow, GAME, VAULT};
 
 #[derive(Accounts)]
 pub struct CancelGame<'info> {
@@ -8,6 +9,15 @@ pub struct CancelGame<'info> {
     pub player: Signer<'info>,
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump, close = player)]
     pub game: Account<'info, Game>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = player, token::token_program = token_program)]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 impl<'info> CancelGame<'info> {
@@ -19,12 +29,40 @@ impl<'info> CancelGame<'info> {
             LudoError::GameAlreadyStarted
         );
 
-        require!(game.cur_player == 1, LudoError::AnotherPlayerAlreadyJoined);
+        require!(
+            game.joined_count == 1,
+            LudoError::AnotherPlayerAlreadyJoined
+        );
 
         require!(
             game.players[color as usize] == self.player.key(),
             LudoError::WrongPlayer
         );
+
+        let player = self.player.to_account_info();
+        match game.mint {
+            None => withdraw(
+                game,
+                &self.vault,
+                &player,
+                &self.system_program,
+                self.vault.lamports(),
+            )?,
+            Some(_) => {
+                let player_token_account = self
+                    .player_token_account
+                    .as_ref()
+                    .ok_or(LudoError::MissingTokenAccounts)?;
+                let escrow =
+                    TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?;
+                escrow.withdraw(
+                    game,
+                    &player_token_account.to_account_info(),
+                    escrow.vault.amount,
+                )?;
+                escrow.close(game, &player)?;
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    emit_event, Game, GameFinished, GameState, LudoError, PlayerForfeited, RandomnessMode,
+    TurnTimedOut, GAME,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct ClaimTimeout<'info> {
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+}
+
+impl<'info> ClaimTimeout<'info> {
+    /// Skips a current player who let `turn_timeout` pass, or forfeits them after
+    /// `max_missed_turns` timeouts in a row. Anyone but the idle player may call it.
+    pub fn claim_timeout(&mut self) -> Result<()> {
+        let game = &mut self.game;
+
+        // commit-reveal rounds time out through claim_roll_timeout
+        require!(
+            game.game_state == GameState::Move
+                || (game.game_state == GameState::RollDice
+                    && game.randomness != RandomnessMode::CommitReveal),
+            LudoError::WrongGameState
+        );
+
+        let idle_player = game.cur_player_key();
+        require!(idle_player != self.player.key(), LudoError::WrongPlayer);
+
+        require!(
+            Clock::get()?.unix_timestamp > game.turn_deadline,
+            LudoError::TurnNotExpired
+        );
+
+        let color = game.cur_player;
+        game.missed_turns[color as usize] = game.missed_turns[color as usize].saturating_add(1);
+        let missed_turns = game.missed_turns[color as usize];
+
+        emit_event!(
+            self,
+            TurnTimedOut {
+                game: game.key(),
+                color,
+                missed_turns,
+            }
+        );
+
+        let mut board = game.board();
+        if game.max_missed_turns != 0 && missed_turns >= game.max_missed_turns {
+            board.forfeit(color);
+            game.set_board(&board);
+            emit_event!(
+                self,
+                PlayerForfeited {
+                    game: game.key(),
+                    player: idle_player,
+                    color,
+                    place: game.places[color as usize],
+                }
+            );
+        } else {
+            ludo_engine::skip_turn(&mut board);
+            game.set_board(&board);
+        }
+
+        if board.is_finished() {
+            game.game_state = GameState::Finished;
+            emit_event!(
+                self,
+                GameFinished {
+                    game: game.key(),
+                    places: game.places,
+                }
+            );
+        } else {
+            game.game_state = GameState::RollDice;
+        }
+        game.touch()?;
+        Ok(())
+    }
+}
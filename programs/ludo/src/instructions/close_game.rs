@@ -1,11 +1,73 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::{Game, GAME};
+use crate::{
+    emit_event, refund_all, refund_all_tokens, Game, GameClosed, GameState, LudoError, TokenEscrow,
+    GAME, VAULT,
+};
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct CloseGame<'info> {
-    #[account(mut)]
     pub player: Signer<'info>,
-    #[account(mut, close = player, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    #[account(mut, address = game.creator)]
+    pub creator: SystemAccount<'info>,
+    #[account(mut, close = creator, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+impl<'info> CloseGame<'info> {
+    /// Closes a finished game once it is settled and recorded, or one abandoned for `ABANDONED_GAME_TIMEOUT`.
+    /// Rent always goes back to the creator. `recipients` are only needed to refund an
+    /// abandoned staked game: the seated players' wallets (token accounts for token games)
+    /// in color order.
+    pub fn close_game(&mut self, recipients: &[AccountInfo<'info>]) -> Result<()> {
+        let game = &mut self.game;
+
+        let abandoned = game.is_abandoned()?;
+        require!(
+            game.game_state == GameState::Finished || abandoned,
+            LudoError::GameNotFinished
+        );
+        require!(game.settled || abandoned, LudoError::GameNotSettled);
+        // the result has to reach the player profiles, see `settle_game`, before the game
+        // can go away
+        require!(game.stats_recorded || abandoned, LudoError::GameNotRecorded);
+
+        let creator = self.creator.to_account_info();
+        match game.mint {
+            None if !game.settled => {
+                refund_all(game, &self.vault, recipients, &self.system_program)?
+            }
+            None => {}
+            Some(_) => {
+                let escrow =
+                    TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?;
+                if !game.settled {
+                    refund_all_tokens(game, &escrow, recipients)?;
+                }
+                escrow.close(game, &creator)?;
+            }
+        }
+
+        emit_event!(
+            self,
+            GameClosed {
+                game: game.key(),
+                seed: game.seed,
+                creator: game.creator,
+                players: game.players,
+                places: game.places,
+                abandoned,
+            }
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,212 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use ephemeral_vrf_sdk::rnd::random_u8_with_range;
+
+use crate::{
+    emit_event, DiceCommitted, DiceRevealed, DiceRolled, Game, GameFinished, GameStarted,
+    GameState, LudoError, PlayerForfeited, RandomnessMode, TurnSkipped, TurnTimedOut, GAME,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct CommitReveal<'info> {
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+}
+
+impl<'info> CommitReveal<'info> {
+    fn check_round(&self, revealing: bool) -> Result<()> {
+        let game = &self.game;
+        require!(
+            game.randomness == RandomnessMode::CommitReveal,
+            LudoError::WrongRandomnessMode
+        );
+        require!(
+            matches!(game.game_state, GameState::Starting | GameState::RollDice)
+                && game.revealing == revealing,
+            LudoError::WrongGameState
+        );
+        Ok(())
+    }
+
+    /// Commits `hash(secret || player)` for the next roll, or for the first player while
+    /// `Starting`. Every active player has to commit before anyone reveals.
+    pub fn commit_dice(&mut self, commitment: [u8; 32]) -> Result<()> {
+        self.check_round(false)?;
+        require!(commitment != [0; 32], LudoError::InvalidCommitment);
+
+        let game = &mut self.game;
+        let color = game
+            .color_of(&self.player.key())
+            .filter(|&color| game.board().is_active(color))
+            .ok_or(LudoError::WrongPlayer)?;
+
+        game.open_round()?;
+        require!(
+            game.commitments[color as usize] == [0; 32],
+            LudoError::InvalidCommitment
+        );
+        game.commitments[color as usize] = commitment;
+        game.missed_turns[color as usize] = 0;
+
+        if !game.pending_players().contains(&true) {
+            game.revealing = true;
+            game.touch()?;
+        }
+
+        emit_event!(
+            self,
+            DiceCommitted {
+                game: game.key(),
+                color,
+            }
+        );
+        Ok(())
+    }
+
+    /// Reveals the secret behind the player's commitment. The last reveal settles the roll.
+    pub fn reveal_dice(&mut self, secret: [u8; 32]) -> Result<()> {
+        self.check_round(true)?;
+
+        let game = &mut self.game;
+        let player = self.player.key();
+        let color = game.color_of(&player).ok_or(LudoError::WrongPlayer)?;
+        require!(
+            game.pending_players()[color as usize]
+                && hashv(&[&secret, player.as_ref()]).to_bytes()
+                    == game.commitments[color as usize],
+            LudoError::InvalidReveal
+        );
+
+        game.commitments[color as usize] = [0; 32];
+        game.randomness_mix = hashv(&[&game.randomness_mix, &secret]).to_bytes();
+
+        emit_event!(
+            self,
+            DiceRevealed {
+                game: game.key(),
+                color,
+                secret,
+            }
+        );
+
+        if game.pending_players().contains(&true) {
+            return Ok(());
+        }
+        game.reset_round();
+
+        if game.game_state == GameState::Starting {
+            let rnd = random_u8_with_range(&game.randomness_mix, 0, game.num_players - 1);
+            game.start(rnd)?;
+            emit_event!(
+                self,
+                GameStarted {
+                    game: game.key(),
+                    first_player: game.cur_player,
+                    randomness: game.randomness_mix,
+                }
+            );
+            return Ok(());
+        }
+
+        let roll = random_u8_with_range(&game.randomness_mix, 1, 6);
+        let color = game.cur_player;
+        emit_event!(
+            self,
+            DiceRolled {
+                game: game.key(),
+                color,
+                roll,
+                turn: game.rolls,
+                randomness: game.randomness_mix,
+            }
+        );
+        if game.land_roll(roll)? {
+            emit_event!(
+                self,
+                TurnSkipped {
+                    game: game.key(),
+                    color,
+                    roll,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Restarts a round stuck past the turn deadline. Players who did not commit miss a turn
+    /// as in `claim_timeout`, players who committed but held back their reveal forfeit.
+    /// Anyone may call it.
+    pub fn claim_roll_timeout(&mut self) -> Result<()> {
+        let revealing = self.game.revealing;
+        self.check_round(revealing)?;
+
+        let game = &mut self.game;
+        require!(
+            Clock::get()?.unix_timestamp > game.turn_deadline,
+            LudoError::TurnNotExpired
+        );
+
+        let pending = game.pending_players();
+        let mut board = game.board();
+        let idle = board.cur_player;
+        for color in (0..4).filter(|&color| pending[color as usize]) {
+            if !board.is_active(color) {
+                continue;
+            }
+            let forfeit = if revealing {
+                true
+            } else {
+                game.missed_turns[color as usize] =
+                    game.missed_turns[color as usize].saturating_add(1);
+                let missed_turns = game.missed_turns[color as usize];
+                emit_event!(
+                    self,
+                    TurnTimedOut {
+                        game: game.key(),
+                        color,
+                        missed_turns,
+                    }
+                );
+                game.max_missed_turns != 0 && missed_turns >= game.max_missed_turns
+            };
+            if forfeit {
+                board.forfeit(color);
+                emit_event!(
+                    self,
+                    PlayerForfeited {
+                        game: game.key(),
+                        player: game.players[color as usize],
+                        color,
+                        place: board.places[color as usize],
+                    }
+                );
+            }
+        }
+
+        if board.is_finished() {
+            game.set_board(&board);
+            game.game_state = GameState::Finished;
+            emit_event!(
+                self,
+                GameFinished {
+                    game: game.key(),
+                    places: game.places,
+                }
+            );
+        } else {
+            // the idle roller loses the roll, unless a forfeit already passed the dice on
+            if !revealing
+                && game.game_state == GameState::RollDice
+                && pending[idle as usize]
+                && board.cur_player == idle
+            {
+                ludo_engine::skip_turn(&mut board);
+            }
+            game.set_board(&board);
+        }
+        game.reset_round();
+        game.touch()
+    }
+}
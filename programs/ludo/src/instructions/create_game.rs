@@ -1,7 +1,13 @@
 use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::{Colors, Game, GameState, LudoError, GAME};
+use crate::{
+    deposit, emit_event, Colors, Config, Game, GameCreated, GameSettings, LobbyAccess, LudoError,
+    PlayerProfile, RandomnessMode, RuleSet, TokenEscrow, CONFIG, GAME, PROFILE, VAULT,
+};
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 #[instruction(seed: u64)]
 pub struct CreateGame<'info> {
@@ -9,34 +15,99 @@ pub struct CreateGame<'info> {
     pub player: Signer<'info>,
     #[account(init, payer = player, space = 8 + Game::INIT_SPACE, seeds = [GAME, seed.to_le_bytes().as_ref()], bump)]
     pub game: Account<'info, Game>,
+    #[account(init_if_needed, payer = player, space = 8 + PlayerProfile::INIT_SPACE, seeds = [PROFILE, player.key().as_ref()], bump)]
+    pub profile: Account<'info, PlayerProfile>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [VAULT, seed.to_le_bytes().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
     pub system_program: Program<'info, System>,
+    // only for games staked in an SPL token
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(init, payer = player, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = player, token::token_program = token_program)]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
 }
 
 impl<'info> CreateGame<'info> {
+    #[allow(clippy::too_many_arguments)]
     pub fn create_game(
         &mut self,
         seed: u64,
         num_players: u8,
         color: Colors,
+        rules: Option<RuleSet>,
+        entry_fee: u64,
+        payouts: [u16; 3],
+        turn_timeout: i64,
+        max_missed_turns: u8,
+        randomness: RandomnessMode,
+        rated: bool,
+        access: LobbyAccess,
         bumps: &CreateGameBumps,
     ) -> Result<()> {
-        require!(
-            num_players == 2 || num_players == 3 || num_players == 4,
-            LudoError::InvalidNumPlayers
-        );
-        self.game.set_inner(Game {
-            seed,
-            bump: bumps.game,
+        let settings = GameSettings {
             num_players,
-            cur_player: 1,
-            token_positions: [[-1; 4]; 4],
-            game_state: GameState::NotStarted,
-            current_roll: 0,
-            six_count: 0,
-            players: [Pubkey::default(); 4],
-            winner: Pubkey::default(),
-        });
-        self.game.players[color as usize] = self.player.key();
+            rules: rules.unwrap_or(self.config.default_rules),
+            mint: self.mint.as_ref().map(|mint| mint.key()),
+            entry_fee,
+            payouts,
+            protocol_fee_bps: self.config.protocol_fee_bps,
+            turn_timeout,
+            max_missed_turns,
+            randomness,
+            rated,
+            access,
+        };
+        settings.validate()?;
+        self.game.set_inner(Game::new(
+            seed,
+            bumps.game,
+            bumps.vault,
+            self.player.key(),
+            settings,
+        )?);
+        // an allow-list binds the creator's seat too, an invite does not
+        if let LobbyAccess::AllowList(_) = access {
+            access.check(color as u8, &self.player.key(), None)?;
+        }
+        self.game.seat(color as u8, self.player.key())?;
+        self.profile.open(self.player.key(), bumps.profile);
+
+        let player = &self.player;
+        match settings.mint {
+            None => deposit(player, &self.vault, &self.system_program, entry_fee)?,
+            Some(_) => {
+                let player_token_account = self
+                    .player_token_account
+                    .as_ref()
+                    .ok_or(LudoError::MissingTokenAccounts)?;
+                TokenEscrow::new(
+                    &self.game,
+                    &self.mint,
+                    &self.token_vault,
+                    &self.token_program,
+                )?
+                .deposit(player_token_account, player, entry_fee)?;
+            }
+        }
+
+        emit_event!(
+            self,
+            GameCreated {
+                game: self.game.key(),
+                seed,
+                creator: self.player.key(),
+                color: color as u8,
+                num_players,
+                rules: settings.rules,
+                randomness,
+                access,
+            }
+        );
         Ok(())
     }
 }
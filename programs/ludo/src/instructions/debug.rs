@@ -1,25 +1,50 @@
 use anchor_lang::prelude::*;
 
-use crate::{Colors, Game, GameState, GAME};
+use crate::{Colors, Config, Game, GameState, LudoError, CONFIG, GAME};
 
 #[derive(Accounts)]
 pub struct Debug<'info> {
-    #[account(mut)]
+    #[account(mut, address = config.debug_authority @ LudoError::Unauthorized)]
     pub player: Signer<'info>,
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SetDebugAuthority<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [CONFIG], bump = config.bump, has_one = admin @ LudoError::Unauthorized)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> SetDebugAuthority<'info> {
+    pub fn set_debug_authority(&mut self, debug_authority: Pubkey) -> Result<()> {
+        self.config.debug_authority = debug_authority;
+        Ok(())
+    }
 }
 
 impl<'info> Debug<'info> {
-    pub fn join_and_start_game_debug(&mut self, color: Colors, start_player: Colors) -> Result<()> {
+    /// Seats `player` on `color` and hands the dice to `start_player`. Call once per
+    /// seat to fill a table.
+    pub fn join_and_start_game_debug(
+        &mut self,
+        color: Colors,
+        player: Pubkey,
+        start_player: Colors,
+    ) -> Result<()> {
         let game = &mut self.game;
-        game.players[color as usize] = self.player.key();
+        game.seat(color as u8, player)?;
+        game.rated = false;
         game.cur_player = start_player as u8;
         game.game_state = GameState::RollDice;
         Ok(())
     }
 
     pub fn roll_dice_debug(&mut self, roll: u8) -> Result<()> {
+        require!((1..=6).contains(&roll), LudoError::InvalidRoll);
         let game = &mut self.game;
         game.current_roll = roll;
         game.game_state = GameState::Move;
@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::program::Ludo;
+use crate::{Config, LudoError, RuleSet, CONFIG};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(init, payer = admin, space = 8 + Config::INIT_SPACE, seeds = [CONFIG], bump)]
+    pub config: Account<'info, Config>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Ludo>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ LudoError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConfig<'info> {
+    pub fn initialize_config(
+        &mut self,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+        oracle_queues: Vec<Pubkey>,
+        default_rules: RuleSet,
+        bumps: &InitializeConfigBumps,
+    ) -> Result<()> {
+        self.config.bump = bumps.config;
+        self.config.admin = self.admin.key();
+        self.config
+            .set(protocol_fee_bps, treasury, &oracle_queues, default_rules)
+    }
+}
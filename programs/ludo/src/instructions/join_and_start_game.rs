@@ -1,24 +1,49 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use ephemeral_vrf_sdk::anchor::vrf;
 use ephemeral_vrf_sdk::instructions::{create_request_randomness_ix, RequestRandomnessParams};
-use ephemeral_vrf_sdk::types::SerializableAccountMeta;
 
-use crate::{Colors, Game, GameState, LudoError, GAME};
+use crate::{
+    caller_seed, deposit, emit_event, vrf_callback_accounts, Colors, Config, Game, GameStarted,
+    GameState, LudoError, PlayerJoined, PlayerProfile, RandomnessMode, TokenEscrow, CONFIG, GAME,
+    PROFILE, VAULT,
+};
 
 #[vrf]
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct JoinStartGame<'info> {
     #[account(mut)]
     pub player: Signer<'info>,
+    // co-signs the join of a lobby created with an invite
+    pub invite: Option<Signer<'info>>,
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
+    #[account(init_if_needed, payer = player, space = 8 + PlayerProfile::INIT_SPACE, seeds = [PROFILE, player.key().as_ref()], bump)]
+    pub profile: Account<'info, PlayerProfile>,
     /// CHECK: The oracle queue
-    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_QUEUE)]
+    #[account(mut, constraint = config.allows_oracle_queue(&oracle_queue.key()) @ LudoError::OracleQueueNotAllowed)]
     pub oracle_queue: AccountInfo<'info>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = player, token::token_program = token_program)]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 impl<'info> JoinStartGame<'info> {
-    pub fn join_and_start_game(&mut self, color: Colors, client_seed: u8) -> Result<()> {
+    pub fn join_and_start_game(
+        &mut self,
+        color: Colors,
+        client_seed: [u8; 32],
+        bumps: &JoinStartGameBumps,
+    ) -> Result<()> {
         let game: &mut Account<'info, Game> = &mut self.game;
 
         require!(
@@ -27,39 +52,56 @@ impl<'info> JoinStartGame<'info> {
         );
 
         require!(
-            game.cur_player + 1 == game.num_players,
-            LudoError::NeedToRunJoin
-        );
-
-        let player = &self.player;
-
-        require!(
-            !game.players.contains(&player.key()),
-            LudoError::PlayerAlreadyJoined
+            game.randomness != RandomnessMode::CommitReveal,
+            LudoError::WrongRandomnessMode
         );
 
         require!(
-            game.players[color as usize] == Pubkey::default(),
-            LudoError::ColorAlreadyTaken
+            game.joined_count + 1 == game.num_players,
+            LudoError::NeedToRunJoin
         );
 
-        game.players[color as usize] = player.key();
+        let player = &self.player;
+        game.access.check(
+            color as u8,
+            &player.key(),
+            self.invite.as_ref().map(|invite| invite.key),
+        )?;
+        game.seat(color as u8, player.key())?;
+        self.profile.open(player.key(), bumps.profile);
 
-        game.cur_player = 0;
         game.game_state = GameState::Starting;
+        game.touch()?;
+        let request = game.request_randomness();
+
+        match game.mint {
+            None => deposit(player, &self.vault, &self.system_program, game.entry_fee)?,
+            Some(_) => {
+                let player_token_account = self
+                    .player_token_account
+                    .as_ref()
+                    .ok_or(LudoError::MissingTokenAccounts)?;
+                TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?
+                    .deposit(player_token_account, player, game.entry_fee)?;
+            }
+        }
+
+        emit_event!(
+            self,
+            PlayerJoined {
+                game: game.key(),
+                player: player.key(),
+                color: color as u8,
+            }
+        );
 
         let ix = create_request_randomness_ix(RequestRandomnessParams {
             payer: self.player.key(),
             oracle_queue: self.oracle_queue.key(),
             callback_program_id: crate::ID,
             callback_discriminator: crate::instruction::CallbackStartGame::DISCRIMINATOR.to_vec(),
-            caller_seed: [client_seed; 32],
-            // Specify any account that is required by the callback
-            accounts_metas: Some(vec![SerializableAccountMeta {
-                pubkey: self.game.key(),
-                is_signer: false,
-                is_writable: true,
-            }]),
+            caller_seed: caller_seed(&client_seed, &self.game, &self.player.key()),
+            accounts_metas: Some(vrf_callback_accounts(self.game.key(), request)),
             ..Default::default()
         });
         self.invoke_signed_vrf(&self.player.to_account_info(), &ix)?;
@@ -68,32 +110,47 @@ impl<'info> JoinStartGame<'info> {
     }
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct CallbackStartGameCtx<'info> {
     /// This check ensure that the vrf_program_identity (which is a PDA) is a singer
     /// enforcing the callback is executed by the VRF program through CPI
     #[account(address = ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY)]
     pub vrf_program_identity: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [GAME, game.seed.to_le_bytes().as_ref()],
+        bump = game.bump,
+        constraint = game.game_state == GameState::Starting @ LudoError::UnexpectedRandomness,
+    )]
     pub game: Account<'info, Game>,
+    /// CHECK: Only the key is used, as the id of the pending randomness request
+    #[account(constraint = game.pending_request == Some(request.key()) @ LudoError::UnexpectedRandomness)]
+    pub request: UncheckedAccount<'info>,
 }
 
 impl<'info> CallbackStartGameCtx<'info> {
     pub fn callback_start_game(&mut self, randomness: [u8; 32]) -> Result<()> {
         let game = &mut self.game;
+        game.pending_request = None;
         let rnd_u8 =
             ephemeral_vrf_sdk::rnd::random_u8_with_range(&randomness, 0, game.num_players - 1);
         msg!("Consuming random number: {:?}", rnd_u8);
 
-        if game.cur_player_key() == Pubkey::default() {
-            game.next_player();
+        if game.randomness == RandomnessMode::HashChain {
+            game.randomness_mix = randomness;
+            game.chain_ready = [true; 4];
         }
-
-        for _ in 0..rnd_u8 {
-            game.next_player();
-        }
-
-        game.game_state = GameState::RollDice;
+        game.start(rnd_u8)?;
+
+        emit_event!(
+            self,
+            GameStarted {
+                game: game.key(),
+                first_player: game.cur_player,
+                randomness,
+            }
+        );
         Ok(())
     }
 }
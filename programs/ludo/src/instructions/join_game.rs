@@ -1,17 +1,35 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-use crate::{Colors, Game, GameState, LudoError, GAME};
+use crate::{
+    deposit, emit_event, Colors, Game, GameState, LudoError, PlayerJoined, PlayerProfile,
+    RandomnessMode, TokenEscrow, GAME, PROFILE, VAULT,
+};
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct JoinGame<'info> {
     #[account(mut)]
     pub player: Signer<'info>,
+    // co-signs the join of a lobby created with an invite
+    pub invite: Option<Signer<'info>>,
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
+    #[account(init_if_needed, payer = player, space = 8 + PlayerProfile::INIT_SPACE, seeds = [PROFILE, player.key().as_ref()], bump)]
+    pub profile: Account<'info, PlayerProfile>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = player, token::token_program = token_program)]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 impl<'info> JoinGame<'info> {
-    pub fn join_game(&mut self, color: Colors) -> Result<()> {
+    pub fn join_game(&mut self, color: Colors, bumps: &JoinGameBumps) -> Result<()> {
         let game = &mut self.game;
 
         require!(
@@ -19,27 +37,46 @@ impl<'info> JoinGame<'info> {
             LudoError::GameAlreadyStarted
         );
 
+        // VRF games are started by join_and_start_game, commit-reveal ones fill up here
         require!(
-            game.cur_player + 1 < game.num_players,
+            game.joined_count + 1 < game.num_players
+                || game.randomness == RandomnessMode::CommitReveal,
             LudoError::NeedToRunJoinAndStart
         );
 
         let player = &self.player;
+        game.access.check(
+            color as u8,
+            &player.key(),
+            self.invite.as_ref().map(|invite| invite.key),
+        )?;
+        game.seat(color as u8, player.key())?;
+        self.profile.open(player.key(), bumps.profile);
+        if game.joined_count == game.num_players {
+            game.game_state = GameState::Starting;
+        }
+        game.touch()?;
 
-        require!(
-            !game.players.contains(&player.key()),
-            LudoError::PlayerAlreadyJoined
-        );
+        match game.mint {
+            None => deposit(player, &self.vault, &self.system_program, game.entry_fee)?,
+            Some(_) => {
+                let player_token_account = self
+                    .player_token_account
+                    .as_ref()
+                    .ok_or(LudoError::MissingTokenAccounts)?;
+                TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?
+                    .deposit(player_token_account, player, game.entry_fee)?;
+            }
+        }
 
-        require!(
-            game.players[color as usize] == Pubkey::default(),
-            LudoError::ColorAlreadyTaken
+        emit_event!(
+            self,
+            PlayerJoined {
+                game: game.key(),
+                player: player.key(),
+                color: color as u8,
+            }
         );
-
-        game.players[color as usize] = player.key();
-
-        game.cur_player += 1;
-
         Ok(())
     }
 }
@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    emit_event, withdraw, Colors, Game, GameState, LudoError, PlayerLeft, TokenEscrow, GAME, VAULT,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct LeaveGame<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = player, token::token_program = token_program)]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+impl<'info> LeaveGame<'info> {
+    pub fn leave_game(&mut self, color: Colors) -> Result<()> {
+        let game = &mut self.game;
+
+        require!(
+            game.game_state == GameState::NotStarted,
+            LudoError::GameAlreadyStarted
+        );
+
+        let player = self.player.key();
+
+        require!(
+            game.players[color as usize] == player,
+            LudoError::WrongPlayer
+        );
+
+        // the creator backs out with cancel_game instead
+        require!(player != game.creator, LudoError::CreatorCannotLeave);
+
+        game.unseat(color as u8);
+        game.touch()?;
+
+        let to = self.player.to_account_info();
+        match game.mint {
+            None => withdraw(game, &self.vault, &to, &self.system_program, game.entry_fee)?,
+            Some(_) => {
+                let player_token_account = self
+                    .player_token_account
+                    .as_ref()
+                    .ok_or(LudoError::MissingTokenAccounts)?;
+                TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?
+                    .withdraw(
+                        game,
+                        &player_token_account.to_account_info(),
+                        game.entry_fee,
+                    )?;
+            }
+        }
+
+        emit_event!(
+            self,
+            PlayerLeft {
+                game: game.key(),
+                player,
+                color: color as u8,
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use ephemeral_vrf_sdk::anchor::vrf;
+use ephemeral_vrf_sdk::instructions::{create_request_randomness_ix, RequestRandomnessParams};
+
+use crate::{
+    caller_seed, emit_event, seat_colors, vrf_callback_accounts, Config, Game, GameMatched,
+    GameState, LudoError, MatchQueue, PlayerDequeued, PlayerProfile, PlayerQueued, QueueEntry,
+    RuleSet, CONFIG, GAME, MAX_QUEUE_LEN, PROFILE, QUEUE, VAULT,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(num_players: u8, entry_fee: u64, rules: RuleSet, bracketed: bool)]
+pub struct Enqueue<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(init_if_needed, payer = player, space = 8 + MatchQueue::INIT_SPACE, seeds = [QUEUE, [num_players].as_ref(), entry_fee.to_le_bytes().as_ref(), rules.key().as_ref(), [bracketed as u8].as_ref()], bump)]
+    pub queue: Account<'info, MatchQueue>,
+    #[account(init_if_needed, payer = player, space = 8 + PlayerProfile::INIT_SPACE, seeds = [PROFILE, player.key().as_ref()], bump)]
+    pub profile: Account<'info, PlayerProfile>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct Dequeue<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [QUEUE, [queue.num_players].as_ref(), queue.entry_fee.to_le_bytes().as_ref(), queue.rules.key().as_ref(), [queue.bracketed as u8].as_ref()], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+}
+
+#[vrf]
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MatchPlayers<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [QUEUE, [queue.num_players].as_ref(), queue.entry_fee.to_le_bytes().as_ref(), queue.rules.key().as_ref(), [queue.bracketed as u8].as_ref()], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+    #[account(init, payer = payer, space = 8 + Game::INIT_SPACE, seeds = [GAME, seed.to_le_bytes().as_ref()], bump)]
+    pub game: Account<'info, Game>,
+    #[account(mut, seeds = [VAULT, seed.to_le_bytes().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: The oracle queue
+    #[account(mut, constraint = config.allows_oracle_queue(&oracle_queue.key()) @ LudoError::OracleQueueNotAllowed)]
+    pub oracle_queue: AccountInfo<'info>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Enqueue<'info> {
+    /// Waits for a game in the queue of these settings, opening the queue if needed.
+    /// The stake is held by the queue until the player is matched or dequeues.
+    pub fn enqueue(
+        &mut self,
+        num_players: u8,
+        entry_fee: u64,
+        rules: RuleSet,
+        bracketed: bool,
+        bumps: &EnqueueBumps,
+    ) -> Result<()> {
+        let queue = &mut self.queue;
+        if queue.num_players == 0 {
+            queue.bump = bumps.queue;
+            queue.num_players = num_players;
+            queue.entry_fee = entry_fee;
+            queue.rules = rules;
+            queue.bracketed = bracketed;
+            queue.settings(0).validate()?;
+        }
+
+        let player = self.player.key();
+        require!(queue.position(&player).is_none(), LudoError::AlreadyQueued);
+        require!(queue.entries.len() < MAX_QUEUE_LEN, LudoError::QueueFull);
+
+        if entry_fee > 0 {
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.player.to_account_info(),
+                        to: queue.to_account_info(),
+                    },
+                ),
+                entry_fee,
+            )?;
+        }
+        self.profile.open(player, bumps.profile);
+        queue.entries.push(QueueEntry {
+            player,
+            rating: self.profile.rating,
+            enqueued_at: Clock::get()?.unix_timestamp,
+        });
+
+        emit_event!(
+            self,
+            PlayerQueued {
+                queue: queue.key(),
+                player,
+            }
+        );
+        Ok(())
+    }
+}
+
+impl<'info> Dequeue<'info> {
+    /// Leaves the queue with the stake.
+    pub fn dequeue(&mut self) -> Result<()> {
+        let queue = &mut self.queue;
+        let player = self.player.key();
+        let position = queue.position(&player).ok_or(LudoError::NotQueued)?;
+        queue.entries.remove(position);
+
+        queue.sub_lamports(queue.entry_fee)?;
+        self.player.add_lamports(queue.entry_fee)?;
+
+        emit_event!(
+            self,
+            PlayerDequeued {
+                queue: queue.key(),
+                player,
+            }
+        );
+        Ok(())
+    }
+}
+
+impl<'info> MatchPlayers<'info> {
+    /// Creates a game at `seed` for the next table of the queue, moves their stakes
+    /// to its vault and starts it through the VRF like `join_and_start_game`. Anyone may
+    /// call it and pays the rent, getting it back when the game is closed.
+    pub fn match_players(
+        &mut self,
+        seed: u64,
+        client_seed: [u8; 32],
+        bumps: &MatchPlayersBumps,
+    ) -> Result<()> {
+        let queue = &mut self.queue;
+        let table = queue
+            .next_table(Clock::get()?.unix_timestamp)
+            .ok_or(LudoError::NotEnoughPlayers)?;
+        let matched: Vec<QueueEntry> = table.iter().map(|&i| queue.entries[i]).collect();
+        let mut i = 0;
+        queue.entries.retain(|_| {
+            i += 1;
+            !table.contains(&(i - 1))
+        });
+
+        let game = &mut self.game;
+        game.set_inner(Game::new(
+            seed,
+            bumps.game,
+            bumps.vault,
+            self.payer.key(),
+            queue.settings(self.config.protocol_fee_bps),
+        )?);
+        for (entry, &color) in matched.iter().zip(seat_colors(queue.num_players)) {
+            game.seat(color, entry.player)?;
+        }
+
+        let stakes = queue.entry_fee * matched.len() as u64;
+        queue.sub_lamports(stakes)?;
+        self.vault.add_lamports(stakes)?;
+
+        game.game_state = GameState::Starting;
+        game.touch()?;
+        let request = game.request_randomness();
+
+        emit_event!(
+            self,
+            GameMatched {
+                game: game.key(),
+                queue: queue.key(),
+                players: game.players,
+            }
+        );
+
+        let ix = create_request_randomness_ix(RequestRandomnessParams {
+            payer: self.payer.key(),
+            oracle_queue: self.oracle_queue.key(),
+            callback_program_id: crate::ID,
+            callback_discriminator: crate::instruction::CallbackStartGame::DISCRIMINATOR.to_vec(),
+            caller_seed: caller_seed(&client_seed, &self.game, &self.payer.key()),
+            accounts_metas: Some(vrf_callback_accounts(self.game.key(), request)),
+            ..Default::default()
+        });
+        self.invoke_signed_vrf(&self.payer.to_account_info(), &ix)?;
+        Ok(())
+    }
+}
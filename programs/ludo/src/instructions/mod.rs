@@ -1,21 +1,43 @@
 pub mod cancel_game;
+pub mod claim_timeout;
 pub mod close_game;
+pub mod commit_reveal;
 pub mod create_game;
+#[cfg(feature = "debug")]
 pub mod debug;
 pub mod delegate;
+pub mod initialize_config;
 pub mod join_and_start_game;
 pub mod join_game;
+pub mod leave_game;
+pub mod matchmaking;
 pub mod moves;
+pub mod resign;
+pub mod retry_randomness;
 pub mod roll_dice;
+pub mod roll_dice_chain;
+pub mod settle_game;
 pub mod undelegate;
+pub mod update_config;
 
 pub use cancel_game::*;
+pub use claim_timeout::*;
 pub use close_game::*;
+pub use commit_reveal::*;
 pub use create_game::*;
+#[cfg(feature = "debug")]
 pub use debug::*;
 pub use delegate::*;
+pub use initialize_config::*;
 pub use join_and_start_game::*;
 pub use join_game::*;
+pub use leave_game::*;
+pub use matchmaking::*;
 pub use moves::*;
+pub use resign::*;
+pub use retry_randomness::*;
 pub use roll_dice::*;
+pub use roll_dice_chain::*;
+pub use settle_game::*;
 pub use undelegate::*;
+pub use update_config::*;
@@ -1,133 +1,174 @@
 use anchor_lang::prelude::*;
+use ludo_engine::{Action, BoardState};
 
-use crate::{Game, GameState, LudoError, GAME};
-
-pub const SAFE_POSITIONS: [i8; 8] = [0, 13, 26, 39, 8, 21, 34, 47];
+use crate::{
+    emit_event, pay_out, record_stats, CaptureBounced, Config, Game, GameFinished, GameSettled,
+    GameState, LudoError, PlayerFinished, TokenCaptured, TokenEntered, TokenMoved, CONFIG, GAME,
+    VAULT,
+};
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct Move<'info> {
     #[account(mut)]
     pub player: Signer<'info>,
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
+    // only needed to pay out a SOL game on its final move, which is left to
+    // settle_game when the game is delegated or staked in a token
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: Option<SystemAccount<'info>>,
+    pub system_program: Option<Program<'info, System>>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Option<Account<'info, Config>>,
 }
 
 impl<'info> Move<'info> {
     pub fn token_into_play(&mut self, token_num: u8) -> Result<()> {
-        let game = &mut self.game;
-        require!(
-            game.game_state == GameState::Move,
-            LudoError::WrongGameState
-        );
-
-        let cur_player = game.cur_player;
-
-        require!(
-            game.cur_player_key() == self.player.key(),
-            LudoError::WrongPlayer
-        );
-
-        require!(game.current_roll == 6, LudoError::WrongMove);
-
-        // this should never happen because the turn should be skipped in callback_roll_dice
-        require!(game.six_count < 2, LudoError::WrongMove);
-
-        require!(
-            game.token_positions[cur_player as usize][token_num as usize] == -1,
-            LudoError::WrongMove
-        );
-
-        game.token_positions[cur_player as usize][token_num as usize] = 0;
+        self.play(Action::Enter(token_num), &[])
+    }
 
-        game.game_state = GameState::RollDice;
-        Ok(())
+    /// `recipients` are the wallets paid when this move finishes a SOL game, see `pay_out`,
+    /// optionally followed by the player profiles to record the game in, see `record_stats`.
+    /// Both only work for a game played on the base layer; a delegated game is paid out and
+    /// recorded by `settle_game` after `undelegate`.
+    pub fn make_move(&mut self, token_num: u8, recipients: &[AccountInfo<'info>]) -> Result<()> {
+        self.play(Action::Move(token_num), recipients)
     }
 
-    pub fn make_move(&mut self, token_num: u8) -> Result<()> {
+    fn play(&mut self, action: Action, recipients: &[AccountInfo<'info>]) -> Result<()> {
         let game = &mut self.game;
         require!(
             game.game_state == GameState::Move,
             LudoError::WrongGameState
         );
 
-        let cur_player = game.cur_player;
-
         require!(
             game.cur_player_key() == self.player.key(),
             LudoError::WrongPlayer
         );
 
-        // this should never happen because the turn should be skipped in callback_roll_dice
-        require!(
-            (game.six_count == 2 && game.current_roll != 6) || game.six_count < 2,
-            LudoError::WrongMove
-        );
-
-        let cur_position = game.token_positions[cur_player as usize][token_num as usize];
-
-        require!(cur_position != -1, LudoError::WrongMove);
-
-        let new_position = cur_position + game.current_roll as i8;
-
-        require!(new_position <= 56, LudoError::WrongMove);
-
-        game.token_positions[cur_player as usize][token_num as usize] = new_position;
-        if game.token_positions[cur_player as usize]
-            .iter()
-            .all(|&x| x == 56)
-        {
-            game.game_state = GameState::Finished;
-            game.winner = self.player.key();
-            return Ok(());
+        let token = action.token();
+        let mover = game.cur_player as usize;
+
+        let mut board = game.board();
+        // the six limit should never be hit here because the turn is skipped in callback_roll_dice
+        let outcome =
+            ludo_engine::apply(&mut board, game.current_roll, action).map_err(LudoError::from)?;
+        game.set_board(&board);
+        // the partner's color when assisting in teams mode
+        let color = outcome.color;
+        if let Some((captured_color, _)) = outcome.captured {
+            game.captures[mover] += 1;
+            game.captured[captured_color as usize] += 1;
+        }
+        if let Some(defender) = outcome.bounced_by {
+            game.captures[defender as usize] += 1;
+            game.captured[color as usize] += 1;
         }
 
-        if !SAFE_POSITIONS.contains(&new_position) && new_position < 51 {
-            'outer: for defender in 0..4 {
-                if cur_player == defender {
-                    continue;
+        game.game_state = if outcome.game_finished {
+            GameState::Finished
+        } else {
+            GameState::RollDice
+        };
+        game.touch()?;
+
+        match action {
+            Action::Enter(_) => emit_event!(
+                self,
+                TokenEntered {
+                    game: game.key(),
+                    color,
+                    token,
                 }
-                if game.players[defender as usize] == Pubkey::default() {
-                    continue;
+            ),
+            Action::Move(_) => emit_event!(
+                self,
+                TokenMoved {
+                    game: game.key(),
+                    color,
+                    token,
+                    from: outcome.from,
+                    to: outcome.to,
                 }
-                for token in 0..4 {
-                    let def_position = game.token_positions[defender as usize][token];
-                    if def_position == -1 {
-                        continue;
-                    }
-                    let go_home = if cur_player < defender {
-                        new_position + (cur_player as i8 - defender as i8 + 4) * 13 == def_position
-                    } else {
-                        def_position + (defender as i8 - cur_player as i8 + 4) * 13 == new_position
-                    };
-                    if go_home {
-                        // check for blocks
-                        for token2 in 0..4 {
-                            if token2 == token {
-                                continue;
-                            }
-                            // block found
-                            if game.token_positions[defender as usize][token]
-                                == game.token_positions[defender as usize][token2]
-                            {
-                                game.token_positions[cur_player as usize][token_num as usize] = -1;
-                                break 'outer;
-                            }
-                        }
-                        // no blocks
-                        game.token_positions[defender as usize][token] = -1;
-                        break 'outer;
-                    }
+            ),
+        }
+        if let Some((captured_color, captured_token)) = outcome.captured {
+            emit_event!(
+                self,
+                TokenCaptured {
+                    game: game.key(),
+                    color,
+                    token,
+                    captured_color,
+                    captured_token,
+                }
+            );
+        }
+        if let Some(defender) = outcome.bounced_by {
+            emit_event!(
+                self,
+                CaptureBounced {
+                    game: game.key(),
+                    color,
+                    token,
+                    defender,
                 }
+            );
+        }
+        if outcome.player_finished {
+            // a teams player is placed, and announced, once their partner is home as well
+            let finished: &[u8] = if !game.rules.teams {
+                &[color]
+            } else if game.places[color as usize] != 0 {
+                &[BoardState::partner(color), color]
+            } else {
+                &[]
+            };
+            for &color in finished {
+                emit_event!(
+                    self,
+                    PlayerFinished {
+                        game: game.key(),
+                        player: game.players[color as usize],
+                        color,
+                        place: game.places[color as usize],
+                    }
+                );
             }
         }
-
-        if game.current_roll == 6 {
-            game.six_count += 1;
-        } else {
-            game.six_count = 0;
-            game.next_player();
+        if outcome.game_finished {
+            emit_event!(
+                self,
+                GameFinished {
+                    game: game.key(),
+                    places: game.places,
+                }
+            );
+
+            let mut profiles = recipients;
+            if let (false, None, Some(vault), Some(system_program), Some(config)) = (
+                game.settled,
+                game.mint,
+                &self.vault,
+                &self.system_program,
+                &self.config,
+            ) {
+                let settlement = pay_out(game, config, vault, recipients, system_program)?;
+                profiles = &recipients[settlement.payee_count(game)..];
+                emit_event!(
+                    self,
+                    GameSettled {
+                        game: game.key(),
+                        mint: None,
+                        protocol_fee: settlement.protocol_fee,
+                        amounts: settlement.amounts(),
+                    }
+                );
+            }
+            record_stats(game, profiles)?;
         }
-        game.game_state = GameState::RollDice;
         Ok(())
     }
 }
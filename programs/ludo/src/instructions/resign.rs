@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+
+use crate::{emit_event, Game, GameFinished, GameState, LudoError, PlayerResigned, GAME};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct Resign<'info> {
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+}
+
+impl<'info> Resign<'info> {
+    pub fn resign(&mut self) -> Result<()> {
+        let game = &mut self.game;
+
+        require!(
+            matches!(
+                game.game_state,
+                GameState::RollDice | GameState::RollingDice | GameState::Move
+            ),
+            LudoError::WrongGameState
+        );
+
+        let color = game
+            .players
+            .iter()
+            .position(|player| *player == self.player.key())
+            .ok_or(LudoError::WrongPlayer)? as u8;
+
+        let mut board = game.board();
+        require!(board.is_active(color), LudoError::WrongPlayer);
+
+        // the pending roll would otherwise land on the next player
+        require!(
+            !(game.game_state == GameState::RollingDice && game.cur_player == color),
+            LudoError::WrongGameState
+        );
+
+        let cur_player = game.cur_player;
+        board.forfeit(color);
+        game.set_board(&board);
+
+        emit_event!(
+            self,
+            PlayerResigned {
+                game: game.key(),
+                player: self.player.key(),
+                color,
+                place: game.places[color as usize],
+            }
+        );
+
+        if board.is_finished() {
+            game.game_state = GameState::Finished;
+            emit_event!(
+                self,
+                GameFinished {
+                    game: game.key(),
+                    places: game.places,
+                }
+            );
+        } else if cur_player == color {
+            game.game_state = GameState::RollDice;
+        }
+        game.touch()?;
+        Ok(())
+    }
+}
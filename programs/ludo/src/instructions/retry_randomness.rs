@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+use ephemeral_vrf_sdk::anchor::vrf;
+use ephemeral_vrf_sdk::instructions::{create_request_randomness_ix, RequestRandomnessParams};
+
+use crate::{
+    caller_seed, emit_event, vrf_callback_accounts, Config, Game, GameState, LudoError,
+    RandomnessMode, RandomnessRetried, CONFIG, GAME, RANDOMNESS_TIMEOUT,
+};
+
+#[vrf]
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct RetryRandomness<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+    /// CHECK: The oracle queue
+    #[account(mut, constraint = config.allows_oracle_queue(&oracle_queue.key()) @ LudoError::OracleQueueNotAllowed)]
+    pub oracle_queue: AccountInfo<'info>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> RetryRandomness<'info> {
+    /// Requests randomness again when the oracle left the game waiting for longer than
+    /// `RANDOMNESS_TIMEOUT`. Anyone may call it; a late answer to the old request is rejected.
+    pub fn retry_randomness(&mut self, client_seed: [u8; 32]) -> Result<()> {
+        let game: &mut Account<'info, Game> = &mut self.game;
+
+        require!(
+            game.randomness != RandomnessMode::CommitReveal,
+            LudoError::WrongRandomnessMode
+        );
+
+        let callback_discriminator = match game.game_state {
+            GameState::Starting => crate::instruction::CallbackStartGame::DISCRIMINATOR,
+            GameState::RollingDice => crate::instruction::CallbackRollDice::DISCRIMINATOR,
+            _ => return err!(LudoError::WrongGameState),
+        };
+
+        require!(
+            Clock::get()?.unix_timestamp > game.last_activity + RANDOMNESS_TIMEOUT,
+            LudoError::RandomnessNotExpired
+        );
+
+        game.touch()?;
+        let request = game.request_randomness();
+
+        emit_event!(
+            self,
+            RandomnessRetried {
+                game: game.key(),
+                request,
+            }
+        );
+
+        let ix = create_request_randomness_ix(RequestRandomnessParams {
+            payer: self.player.key(),
+            oracle_queue: self.oracle_queue.key(),
+            callback_program_id: crate::ID,
+            callback_discriminator: callback_discriminator.to_vec(),
+            caller_seed: caller_seed(&client_seed, &self.game, &self.player.key()),
+            accounts_metas: Some(vrf_callback_accounts(self.game.key(), request)),
+            ..Default::default()
+        });
+        self.invoke_signed_vrf(&self.player.to_account_info(), &ix)?;
+        Ok(())
+    }
+}
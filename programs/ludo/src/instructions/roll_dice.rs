@@ -1,9 +1,59 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
 use ephemeral_vrf_sdk::anchor::vrf;
 use ephemeral_vrf_sdk::instructions::{create_request_randomness_ix, RequestRandomnessParams};
 use ephemeral_vrf_sdk::types::SerializableAccountMeta;
 
-use crate::{Game, GameState, LudoError, GAME};
+use crate::{
+    emit_event, Config, DiceRolled, Game, GameState, LudoError, RandomnessMode, TurnSkipped,
+    CONFIG, GAME,
+};
+
+/// Accounts passed to the VRF callbacks, the event CPI accounts included when enabled.
+/// `request` is not a real account, only the id of the request the callback answers.
+pub fn vrf_callback_accounts(game: Pubkey, request: Pubkey) -> Vec<SerializableAccountMeta> {
+    #[allow(unused_mut)]
+    let mut accounts = vec![
+        SerializableAccountMeta {
+            pubkey: game,
+            is_signer: false,
+            is_writable: true,
+        },
+        SerializableAccountMeta {
+            pubkey: request,
+            is_signer: false,
+            is_writable: false,
+        },
+    ];
+    #[cfg(feature = "event-cpi")]
+    accounts.extend([
+        SerializableAccountMeta {
+            pubkey: crate::event_authority().0,
+            is_signer: false,
+            is_writable: false,
+        },
+        SerializableAccountMeta {
+            pubkey: crate::ID,
+            is_signer: false,
+            is_writable: false,
+        },
+    ]);
+    accounts
+}
+
+/// Caller seed of a randomness request: the client's seed bound to the game, the turn,
+/// the payer and the request, so every request is unique and can be recomputed off-chain.
+pub fn caller_seed(client_seed: &[u8; 32], game: &Account<Game>, player: &Pubkey) -> [u8; 32] {
+    let request = game.pending_request.unwrap_or_default();
+    hashv(&[
+        client_seed,
+        game.key().as_ref(),
+        &game.rolls.to_le_bytes(),
+        player.as_ref(),
+        request.as_ref(),
+    ])
+    .to_bytes()
+}
 
 #[vrf]
 #[derive(Accounts)]
@@ -13,12 +63,14 @@ pub struct RollDiceDelegateCtx<'info> {
     #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
     pub game: Account<'info, Game>,
     /// CHECK: The oracle queue
-    #[account(mut, address = ephemeral_vrf_sdk::consts::DEFAULT_EPHEMERAL_QUEUE)]
+    #[account(mut, constraint = config.allows_oracle_queue(&oracle_queue.key()) @ LudoError::OracleQueueNotAllowed)]
     pub oracle_queue: AccountInfo<'info>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
 }
 
 impl<'info> RollDiceDelegateCtx<'info> {
-    pub fn roll_dice_delegate(&mut self, client_seed: u8) -> Result<()> {
+    pub fn roll_dice_delegate(&mut self, client_seed: [u8; 32]) -> Result<()> {
         let game: &mut Account<'info, Game> = &mut self.game;
 
         require!(
@@ -26,12 +78,18 @@ impl<'info> RollDiceDelegateCtx<'info> {
             LudoError::WrongGameState
         );
 
+        game.check_vrf_roll()?;
+
         require!(
             game.cur_player_key() == self.player.key(),
             LudoError::WrongPlayer
         );
 
         game.game_state = GameState::RollingDice;
+        let cur_player = game.cur_player;
+        game.missed_turns[cur_player as usize] = 0;
+        game.touch()?;
+        let request = game.request_randomness();
 
         msg!("Requesting randomness...");
         let ix = create_request_randomness_ix(RequestRandomnessParams {
@@ -39,13 +97,8 @@ impl<'info> RollDiceDelegateCtx<'info> {
             oracle_queue: self.oracle_queue.key(),
             callback_program_id: crate::ID,
             callback_discriminator: crate::instruction::CallbackRollDice::DISCRIMINATOR.to_vec(),
-            caller_seed: [client_seed; 32],
-            // Specify any account that is required by the callback
-            accounts_metas: Some(vec![SerializableAccountMeta {
-                pubkey: game.key(),
-                is_signer: false,
-                is_writable: true,
-            }]),
+            caller_seed: caller_seed(&client_seed, game, &self.player.key()),
+            accounts_metas: Some(vrf_callback_accounts(game.key(), request)),
             ..Default::default()
         });
         self.invoke_signed_vrf(&self.player.to_account_info(), &ix)?;
@@ -53,14 +106,23 @@ impl<'info> RollDiceDelegateCtx<'info> {
     }
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct CallbackRollDiceCtx<'info> {
     /// This check ensure that the vrf_program_identity (which is a PDA) is a singer
     /// enforcing the callback is executed by the VRF program through CPI
     #[account(address = ephemeral_vrf_sdk::consts::VRF_PROGRAM_IDENTITY)]
     pub vrf_program_identity: Signer<'info>,
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [GAME, game.seed.to_le_bytes().as_ref()],
+        bump = game.bump,
+        constraint = game.game_state == GameState::RollingDice @ LudoError::UnexpectedRandomness,
+    )]
     pub game: Account<'info, Game>,
+    /// CHECK: Only the key is used, as the id of the pending randomness request
+    #[account(constraint = game.pending_request == Some(request.key()) @ LudoError::UnexpectedRandomness)]
+    pub request: UncheckedAccount<'info>,
 }
 
 impl<'info> CallbackRollDiceCtx<'info> {
@@ -69,27 +131,33 @@ impl<'info> CallbackRollDiceCtx<'info> {
         msg!("Consuming random number: {:?}", roll);
 
         let game = &mut self.game;
+        game.pending_request = None;
+        let color = game.cur_player;
+        if game.randomness == RandomnessMode::HashChain {
+            game.randomness_mix = hashv(&[&game.randomness_mix, &randomness]).to_bytes();
+            game.chain_ready = [true; 4];
+        }
 
-        let mut skip_turn = true;
-
-        if !(roll == 6 && game.six_count == 2) {
-            for token in game.token_positions[game.cur_player as usize].iter() {
-                if *token == -1 && roll != 6 || *token == 56 {
-                    continue;
-                }
-                if *token + roll as i8 <= 56 {
-                    skip_turn = false;
-                }
+        emit_event!(
+            self,
+            DiceRolled {
+                game: game.key(),
+                color,
+                roll,
+                turn: game.rolls,
+                randomness,
             }
-        }
+        );
 
-        if skip_turn {
-            game.six_count = 0;
-            game.next_player();
-            game.game_state = GameState::RollDice;
-        } else {
-            game.current_roll = roll;
-            game.game_state = GameState::Move;
+        if game.land_roll(roll)? {
+            emit_event!(
+                self,
+                TurnSkipped {
+                    game: game.key(),
+                    color,
+                    roll,
+                }
+            );
         }
         Ok(())
     }
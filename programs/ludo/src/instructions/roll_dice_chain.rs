@@ -0,0 +1,122 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use ephemeral_vrf_sdk::rnd::random_u8_with_range;
+
+use crate::{
+    emit_event, DiceCommitted, DiceRolled, Game, GameState, LudoError, RandomnessMode, TurnSkipped,
+    GAME,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct RollDiceChain<'info> {
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+}
+
+impl<'info> RollDiceChain<'info> {
+    /// Commits `hash(secret || player)` for the player's next chain roll, replacing an
+    /// earlier commitment that is not ready yet. It can be revealed once randomness the
+    /// player could not know has entered the chain: the VRF that starts the game, another
+    /// player's roll or a VRF roll. From then on it binds, see `chain_commitment_binds`.
+    pub fn commit_dice_chain(&mut self, commitment: [u8; 32]) -> Result<()> {
+        let game = &mut self.game;
+
+        require!(
+            game.randomness == RandomnessMode::HashChain,
+            LudoError::WrongRandomnessMode
+        );
+        require!(
+            game.game_state != GameState::Finished,
+            LudoError::WrongGameState
+        );
+        require!(commitment != [0; 32], LudoError::InvalidCommitment);
+
+        let color = game
+            .color_of(&self.player.key())
+            .ok_or(LudoError::WrongPlayer)?;
+        require!(
+            !game.chain_commitment_binds(color),
+            LudoError::CommitmentReady
+        );
+        game.commitments[color as usize] = commitment;
+        game.chain_ready[color as usize] = false;
+
+        emit_event!(
+            self,
+            DiceCommitted {
+                game: game.key(),
+                color,
+            }
+        );
+        Ok(())
+    }
+
+    /// Rolls in a single transaction by extending the game's hash chain with the turn
+    /// number, the player and `secret`, which must match a ready commitment. Without one
+    /// the player rolls with `roll_dice_delegate`. `next_commitment` replaces the spent one.
+    pub fn roll_dice_chain(&mut self, secret: [u8; 32], next_commitment: [u8; 32]) -> Result<()> {
+        let game = &mut self.game;
+
+        require!(
+            game.randomness == RandomnessMode::HashChain,
+            LudoError::WrongRandomnessMode
+        );
+
+        require!(
+            game.game_state == GameState::RollDice,
+            LudoError::WrongGameState
+        );
+
+        let player = self.player.key();
+        require!(game.cur_player_key() == player, LudoError::WrongPlayer);
+        require!(next_commitment != [0; 32], LudoError::InvalidCommitment);
+
+        let color = game.cur_player;
+        let commitment = game.commitments[color as usize];
+        require!(
+            commitment != [0; 32] && game.chain_ready[color as usize],
+            LudoError::CommitmentNotReady
+        );
+        require!(
+            hashv(&[&secret, player.as_ref()]).to_bytes() == commitment,
+            LudoError::InvalidReveal
+        );
+        game.commitments[color as usize] = next_commitment;
+        // the secret is news to everyone else, while the roller knows the chain it leaves
+        game.chain_ready = core::array::from_fn(|other| other != color as usize);
+        game.missed_turns[color as usize] = 0;
+
+        game.randomness_mix = hashv(&[
+            &game.randomness_mix,
+            &game.rolls.to_le_bytes(),
+            player.as_ref(),
+            &secret,
+        ])
+        .to_bytes();
+        let roll = random_u8_with_range(&game.randomness_mix, 1, 6);
+
+        emit_event!(
+            self,
+            DiceRolled {
+                game: game.key(),
+                color,
+                roll,
+                turn: game.rolls,
+                randomness: game.randomness_mix,
+            }
+        );
+        if game.land_roll(roll)? {
+            emit_event!(
+                self,
+                TurnSkipped {
+                    game: game.key(),
+                    color,
+                    roll,
+                }
+            );
+        }
+        Ok(())
+    }
+}
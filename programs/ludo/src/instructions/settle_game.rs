@@ -0,0 +1,70 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    emit_event, pay_out, pay_out_tokens, record_stats, Config, Game, GameSettled, GameState,
+    LudoError, TokenEscrow, CONFIG, GAME, VAULT,
+};
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct SettleGame<'info> {
+    #[account(mut, seeds = [GAME, game.seed.to_le_bytes().as_ref()], bump = game.bump)]
+    pub game: Account<'info, Game>,
+    #[account(seeds = [CONFIG], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [VAULT, game.seed.to_le_bytes().as_ref()], bump = game.vault_bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut, associated_token::mint = mint, associated_token::authority = game, associated_token::token_program = token_program)]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+impl<'info> SettleGame<'info> {
+    /// Pays out a finished game from the base layer, e.g. after `undelegate`, and records it
+    /// in the player profiles. `recipients` are the wallets (token accounts for token games)
+    /// of the treasury and the paid places, see `Settlement::payees`, followed by the profiles
+    /// of the seated players in color order, see `record_stats`.
+    pub fn settle_game(&mut self, recipients: &[AccountInfo<'info>]) -> Result<()> {
+        let game = &mut self.game;
+        require!(
+            game.game_state == GameState::Finished,
+            LudoError::GameNotFinished
+        );
+
+        let mut profiles = recipients;
+        let paid = !game.settled;
+        if paid {
+            let settlement = match game.mint {
+                None => pay_out(
+                    game,
+                    &self.config,
+                    &self.vault,
+                    recipients,
+                    &self.system_program,
+                )?,
+                Some(_) => {
+                    let escrow =
+                        TokenEscrow::new(game, &self.mint, &self.token_vault, &self.token_program)?;
+                    pay_out_tokens(game, &self.config, &escrow, recipients)?
+                }
+            };
+            profiles = &recipients[settlement.payee_count(game)..];
+            emit_event!(
+                self,
+                GameSettled {
+                    game: game.key(),
+                    mint: game.mint,
+                    protocol_fee: settlement.protocol_fee,
+                    amounts: settlement.amounts(),
+                }
+            );
+        }
+
+        let recorded = record_stats(game, profiles)?;
+        require!(paid || recorded, LudoError::AlreadySettled);
+        Ok(())
+    }
+}
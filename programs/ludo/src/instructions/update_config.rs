@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+
+use crate::{Config, LudoError, RuleSet, CONFIG};
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [CONFIG], bump = config.bump, has_one = admin @ LudoError::Unauthorized)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn update_config(
+        &mut self,
+        new_admin: Pubkey,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+        oracle_queues: Vec<Pubkey>,
+        default_rules: RuleSet,
+    ) -> Result<()> {
+        // the default key has no signer, which would lock the config for good
+        require_keys_neq!(new_admin, Pubkey::default(), LudoError::InvalidAdmin);
+        self.config.admin = new_admin;
+        self.config
+            .set(protocol_fee_bps, treasury, &oracle_queues, default_rules)
+    }
+}
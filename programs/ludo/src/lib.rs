@@ -4,12 +4,18 @@ use ephemeral_rollups_sdk::anchor::ephemeral;
 
 pub mod constants;
 pub mod error;
+pub mod escrow;
+pub mod events;
 pub mod instructions;
+pub mod rating;
 pub mod state;
 
 pub use constants::*;
 pub use error::*;
+pub use escrow::*;
+pub use events::*;
 pub use instructions::*;
+pub use rating::*;
 pub use state::*;
 
 declare_id!("Ab2xsYDGv4GKKJc2wuKiGeqtXhP6ukJFNAHXvoCBVdHG");
@@ -19,14 +25,68 @@ declare_id!("Ab2xsYDGv4GKKJc2wuKiGeqtXhP6ukJFNAHXvoCBVdHG");
 pub mod ludo {
     use super::*;
 
+    #[allow(clippy::too_many_arguments)]
     pub fn create_game(
         ctx: Context<CreateGame>,
         seed: u64,
         num_players: u8,
         color: Colors,
+        rules: Option<RuleSet>,
+        entry_fee: u64,
+        payouts: [u16; 3],
+        turn_timeout: i64,
+        max_missed_turns: u8,
+        randomness: RandomnessMode,
+        rated: bool,
+        access: LobbyAccess,
     ) -> Result<()> {
-        ctx.accounts
-            .create_game(seed, num_players, color, &ctx.bumps)
+        ctx.accounts.create_game(
+            seed,
+            num_players,
+            color,
+            rules,
+            entry_fee,
+            payouts,
+            turn_timeout,
+            max_missed_turns,
+            randomness,
+            rated,
+            access,
+            &ctx.bumps,
+        )
+    }
+
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+        oracle_queues: Vec<Pubkey>,
+        default_rules: RuleSet,
+    ) -> Result<()> {
+        ctx.accounts.initialize_config(
+            protocol_fee_bps,
+            treasury,
+            oracle_queues,
+            default_rules,
+            &ctx.bumps,
+        )
+    }
+
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        new_admin: Pubkey,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+        oracle_queues: Vec<Pubkey>,
+        default_rules: RuleSet,
+    ) -> Result<()> {
+        ctx.accounts.update_config(
+            new_admin,
+            protocol_fee_bps,
+            treasury,
+            oracle_queues,
+            default_rules,
+        )
     }
 
     pub fn cancel_game(ctx: Context<CancelGame>, color: Colors) -> Result<()> {
@@ -34,15 +94,20 @@ pub mod ludo {
     }
 
     pub fn join_game(ctx: Context<JoinGame>, color: Colors) -> Result<()> {
-        ctx.accounts.join_game(color)
+        ctx.accounts.join_game(color, &ctx.bumps)
+    }
+
+    pub fn leave_game(ctx: Context<LeaveGame>, color: Colors) -> Result<()> {
+        ctx.accounts.leave_game(color)
     }
 
     pub fn join_and_start_game(
         ctx: Context<JoinStartGame>,
         color: Colors,
-        client_seed: u8,
+        client_seed: [u8; 32],
     ) -> Result<()> {
-        ctx.accounts.join_and_start_game(color, client_seed)
+        ctx.accounts
+            .join_and_start_game(color, client_seed, &ctx.bumps)
     }
 
     pub fn callback_start_game(
@@ -52,10 +117,41 @@ pub mod ludo {
         ctx.accounts.callback_start_game(randomness)
     }
 
-    pub fn roll_dice_delegate(ctx: Context<RollDiceDelegateCtx>, client_seed: u8) -> Result<()> {
+    pub fn roll_dice_delegate(
+        ctx: Context<RollDiceDelegateCtx>,
+        client_seed: [u8; 32],
+    ) -> Result<()> {
         ctx.accounts.roll_dice_delegate(client_seed)
     }
 
+    pub fn roll_dice_chain(
+        ctx: Context<RollDiceChain>,
+        secret: [u8; 32],
+        next_commitment: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.roll_dice_chain(secret, next_commitment)
+    }
+
+    pub fn commit_dice_chain(ctx: Context<RollDiceChain>, commitment: [u8; 32]) -> Result<()> {
+        ctx.accounts.commit_dice_chain(commitment)
+    }
+
+    pub fn commit_dice(ctx: Context<CommitReveal>, commitment: [u8; 32]) -> Result<()> {
+        ctx.accounts.commit_dice(commitment)
+    }
+
+    pub fn reveal_dice(ctx: Context<CommitReveal>, secret: [u8; 32]) -> Result<()> {
+        ctx.accounts.reveal_dice(secret)
+    }
+
+    pub fn claim_roll_timeout(ctx: Context<CommitReveal>) -> Result<()> {
+        ctx.accounts.claim_roll_timeout()
+    }
+
+    pub fn retry_randomness(ctx: Context<RetryRandomness>, client_seed: [u8; 32]) -> Result<()> {
+        ctx.accounts.retry_randomness(client_seed)
+    }
+
     pub fn callback_roll_dice(
         ctx: Context<CallbackRollDiceCtx>,
         randomness: [u8; 32],
@@ -67,26 +163,55 @@ pub mod ludo {
         ctx.accounts.token_into_play(token_num)
     }
 
-    pub fn make_move(ctx: Context<Move>, token_num: u8) -> Result<()> {
-        ctx.accounts.make_move(token_num)
+    pub fn make_move<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Move<'info>>,
+        token_num: u8,
+    ) -> Result<()> {
+        ctx.accounts.make_move(token_num, ctx.remaining_accounts)
+    }
+
+    pub fn claim_timeout(ctx: Context<ClaimTimeout>) -> Result<()> {
+        ctx.accounts.claim_timeout()
+    }
+
+    pub fn resign(ctx: Context<Resign>) -> Result<()> {
+        ctx.accounts.resign()
     }
 
+    pub fn settle_game<'info>(ctx: Context<'_, '_, 'info, 'info, SettleGame<'info>>) -> Result<()> {
+        ctx.accounts.settle_game(ctx.remaining_accounts)
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn set_debug_authority(
+        ctx: Context<SetDebugAuthority>,
+        debug_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.set_debug_authority(debug_authority)
+    }
+
+    #[cfg(feature = "debug")]
     pub fn join_and_start_game_debug(
         ctx: Context<Debug>,
         color: Colors,
+        player: Pubkey,
         start_player: Colors,
     ) -> Result<()> {
-        ctx.accounts.join_and_start_game_debug(color, start_player)
+        ctx.accounts
+            .join_and_start_game_debug(color, player, start_player)
     }
 
+    #[cfg(feature = "debug")]
     pub fn roll_dice_debug(ctx: Context<Debug>, roll: u8) -> Result<()> {
         ctx.accounts.roll_dice_debug(roll)
     }
 
+    #[cfg(feature = "debug")]
     pub fn next_turn_debug(ctx: Context<Debug>) -> Result<()> {
         ctx.accounts.next_turn_debug()
     }
 
+    #[cfg(feature = "debug")]
     pub fn move_token_debug(
         ctx: Context<Debug>,
         color: Colors,
@@ -96,6 +221,29 @@ pub mod ludo {
         ctx.accounts.move_token_debug(color, token_num, position)
     }
 
+    pub fn enqueue(
+        ctx: Context<Enqueue>,
+        num_players: u8,
+        entry_fee: u64,
+        rules: RuleSet,
+        bracketed: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .enqueue(num_players, entry_fee, rules, bracketed, &ctx.bumps)
+    }
+
+    pub fn dequeue(ctx: Context<Dequeue>) -> Result<()> {
+        ctx.accounts.dequeue()
+    }
+
+    pub fn match_players(
+        ctx: Context<MatchPlayers>,
+        seed: u64,
+        client_seed: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.match_players(seed, client_seed, &ctx.bumps)
+    }
+
     pub fn delegate(ctx: Context<Delegate>) -> Result<()> {
         ctx.accounts.delegate()
     }
@@ -104,7 +252,7 @@ pub mod ludo {
         ctx.accounts.undelegate()
     }
 
-    pub fn close_game(_ctx: Context<CloseGame>) -> Result<()> {
-        Ok(())
+    pub fn close_game<'info>(ctx: Context<'_, '_, 'info, 'info, CloseGame<'info>>) -> Result<()> {
+        ctx.accounts.close_game(ctx.remaining_accounts)
     }
 }
@@ -0,0 +1,156 @@
+//! Elo ratings for tables of up to four: every pair of opponents counts as a game of its
+//! own won by the better place, and a player's K-factor is split between their opponents.
+//! Integer math only, expected scores coming from a table.
+
+use ludo_engine::BoardState;
+
+pub const INITIAL_RATING: u16 = 1200;
+const K_FACTOR: i64 = 32;
+
+const SCORE_SCALE: i64 = 10_000;
+const EXPECTED_STEP: i64 = 25;
+// 1 / (1 + 10^(d / 400)) scaled by SCORE_SCALE for every EXPECTED_STEP of rating difference d
+const EXPECTED_SCORES: [i64; 33] = [
+    5000, 4641, 4285, 3937, 3599, 3275, 2966, 2675, 2403, 2150, 1917, 1704, 1510, 1334, 1177, 1035,
+    909, 797, 698, 610, 532, 464, 405, 352, 307, 267, 232, 201, 175, 152, 132, 114, 99,
+];
+
+/// Expected score out of `SCORE_SCALE` against an opponent rated `diff` higher,
+/// interpolated between table entries and flat past the end of the table.
+fn expected_score(diff: i64) -> i64 {
+    if diff < 0 {
+        return SCORE_SCALE - expected_score(-diff);
+    }
+    let i = (diff / EXPECTED_STEP) as usize;
+    if i + 1 >= EXPECTED_SCORES.len() {
+        return EXPECTED_SCORES[EXPECTED_SCORES.len() - 1];
+    }
+    let offset = diff % EXPECTED_STEP;
+    EXPECTED_SCORES[i] - (EXPECTED_SCORES[i] - EXPECTED_SCORES[i + 1]) * offset / EXPECTED_STEP
+}
+
+/// Rating change of each color from the finishing `places`, `ratings` being `None` for
+/// free seats. Partners in a teams game are not scored against each other.
+pub fn rating_changes(ratings: [Option<u16>; 4], places: [u8; 4], teams: bool) -> [i32; 4] {
+    let opponents = |color: usize| {
+        (0..4).filter(move |&other| {
+            ratings[other].is_some()
+                && other != color
+                && !(teams && other == BoardState::partner(color as u8) as usize)
+        })
+    };
+    let mut changes = [0; 4];
+    for (color, rating) in ratings.iter().enumerate() {
+        let Some(rating) = rating else {
+            continue;
+        };
+        let surplus: i64 = opponents(color)
+            .map(|other| {
+                let score = if places[color] < places[other] {
+                    SCORE_SCALE
+                } else {
+                    0
+                };
+                score - expected_score(ratings[other].unwrap() as i64 - *rating as i64)
+            })
+            .sum();
+        let num = K_FACTOR * surplus;
+        let den = opponents(color).count().max(1) as i64 * SCORE_SCALE;
+        // rounded half away from zero
+        let half = if num < 0 { -den / 2 } else { den / 2 };
+        changes[color] = ((num + half) / den) as i32;
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_score_table() {
+        assert_eq!(expected_score(0), 5000);
+        assert_eq!(expected_score(100), 3599);
+        assert_eq!(expected_score(-100), 6401);
+        assert_eq!(expected_score(400), 909);
+        // 12 / 25 of the way from 100 to 125
+        assert_eq!(expected_score(112), 3444);
+        assert_eq!(expected_score(800), 99);
+        assert_eq!(expected_score(2000), 99);
+        assert_eq!(expected_score(-2000), 9901);
+    }
+
+    #[test]
+    fn two_players() {
+        let even = [Some(1200), None, Some(1200), None];
+        assert_eq!(rating_changes(even, [1, 0, 2, 0], false), [16, 0, -16, 0]);
+
+        // 32 * 0.6401 = 20.48 for the underdog winning
+        let uneven = [Some(1200), None, Some(1300), None];
+        assert_eq!(rating_changes(uneven, [1, 0, 2, 0], false), [20, 0, -20, 0]);
+        // 32 * 0.3599 = 11.52 for the favourite
+        assert_eq!(rating_changes(uneven, [2, 0, 1, 0], false), [-12, 0, 12, 0]);
+
+        // the favourite gains nothing from a far weaker opponent
+        let lopsided = [Some(2400), None, Some(800), None];
+        assert_eq!(rating_changes(lopsided, [1, 0, 2, 0], false), [0, 0, 0, 0]);
+        assert_eq!(
+            rating_changes(lopsided, [2, 0, 1, 0], false),
+            [-32, 0, 32, 0]
+        );
+    }
+
+    #[test]
+    fn three_players() {
+        let ratings = [Some(1200), Some(1200), Some(1200), None];
+        assert_eq!(
+            rating_changes(ratings, [1, 2, 3, 0], false),
+            [16, 0, -16, 0]
+        );
+    }
+
+    #[test]
+    fn four_players() {
+        let ratings = [Some(1200); 4];
+        // 32 / 3 * 0.5 = 5.33 for second place
+        assert_eq!(
+            rating_changes(ratings, [1, 2, 3, 4], false),
+            [16, 5, -5, -16]
+        );
+    }
+
+    #[test]
+    fn teams_skip_partners() {
+        let ratings = [Some(1200); 4];
+        // 0 and 2 beat both opponents, and 2 is not marked down for trailing 0
+        assert_eq!(
+            rating_changes(ratings, [1, 3, 2, 4], true),
+            [16, -16, 16, -16]
+        );
+        assert_eq!(
+            rating_changes(ratings, [1, 3, 2, 4], false),
+            [16, -5, 5, -16]
+        );
+    }
+
+    #[test]
+    fn rounding() {
+        // 20.48 and 11.52 round to the nearest whole point on either side of zero
+        let ratings = [Some(1300), Some(1200), None, None];
+        assert_eq!(
+            rating_changes(ratings, [2, 1, 0, 0], false),
+            [-20, 20, 0, 0]
+        );
+        assert_eq!(
+            rating_changes(ratings, [1, 2, 0, 0], false),
+            [12, -12, 0, 0]
+        );
+
+        // 32 / 2 * (1 - 0.3599 + 1 - 0.2403) = 22.40 for the underdog beating both
+        let ratings = [Some(1200), Some(1300), Some(1100), None];
+        assert_eq!(
+            rating_changes(ratings, [2, 3, 1, 0], false),
+            [0, -22, 22, 0]
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::{LudoError, RuleSet, BPS_DENOMINATOR};
+
+pub const MAX_ORACLE_QUEUES: usize = 4;
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub bump: u8,
+    pub admin: Pubkey,
+    // cut of every staked pot in basis points, sent to `treasury`
+    pub protocol_fee_bps: u16,
+    pub treasury: Pubkey,
+    // VRF oracle queues randomness may be requested from, unused slots are default
+    pub oracle_queues: [Pubkey; MAX_ORACLE_QUEUES],
+    pub default_rules: RuleSet,
+    // only signer allowed to call the debug instructions of a `debug` build
+    pub debug_authority: Pubkey,
+}
+
+impl Config {
+    pub fn set(
+        &mut self,
+        protocol_fee_bps: u16,
+        treasury: Pubkey,
+        oracle_queues: &[Pubkey],
+        default_rules: RuleSet,
+    ) -> Result<()> {
+        require!(
+            protocol_fee_bps <= BPS_DENOMINATOR,
+            LudoError::InvalidProtocolFee
+        );
+        require!(
+            oracle_queues.len() <= MAX_ORACLE_QUEUES,
+            LudoError::TooManyOracleQueues
+        );
+        require!(
+            ludo_engine::Rules::from(default_rules).is_valid(),
+            LudoError::InvalidRuleSet
+        );
+
+        self.protocol_fee_bps = protocol_fee_bps;
+        self.treasury = treasury;
+        self.oracle_queues = [Pubkey::default(); MAX_ORACLE_QUEUES];
+        self.oracle_queues[..oracle_queues.len()].copy_from_slice(oracle_queues);
+        self.default_rules = default_rules;
+        Ok(())
+    }
+
+    pub fn allows_oracle_queue(&self, queue: &Pubkey) -> bool {
+        *queue != Pubkey::default() && self.oracle_queues.contains(queue)
+    }
+}
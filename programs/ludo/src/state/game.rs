@@ -1,34 +1,419 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use ludo_engine::BoardState;
+
+use ludo_engine::Rules;
+
+use crate::{LudoError, RuleSet, ABANDONED_GAME_TIMEOUT, BPS_DENOMINATOR};
 
 #[account]
 #[derive(InitSpace)]
 pub struct Game {
     pub seed: u64,
     pub bump: u8,
+    pub creator: Pubkey,
+    // unix timestamp of the last instruction that changed the game
+    pub last_activity: i64,
+    // seconds the current player has to act before claim_timeout can skip them
+    pub turn_timeout: i64,
+    pub turn_deadline: i64,
+    // timeouts in a row after which a player forfeits, 0 to only ever skip
+    pub max_missed_turns: u8,
+    pub missed_turns: [u8; 4],
     pub num_players: u8,
     pub cur_player: u8,
     pub token_positions: [[i8; 4]; 4],
     pub game_state: GameState,
     pub current_roll: u8,
+    // rolls landed so far, numbering the turns
+    pub rolls: u32,
+    // per color, for the player profiles
+    pub sixes: [u16; 4],
+    pub captures: [u16; 4],
+    pub captured: [u16; 4],
+    // the finished game has been added to the player profiles
+    pub stats_recorded: bool,
+    // the result moves the players' ratings
+    pub rated: bool,
     pub six_count: u8,
+    // where the dice come from, fixed when the game is created
+    pub randomness: RandomnessMode,
+    // randomness requests made so far, each one getting a new id
+    pub vrf_nonce: u64,
+    // id of the randomness request the VRF callback has to answer
+    pub pending_request: Option<Pubkey>,
+    // commit-reveal: hashed secrets of the round, each zeroed again once revealed;
+    // hash chain: each player's hashed secret for their next roll
+    pub commitments: [[u8; 32]; 4],
+    // hash chain: randomness the committer could not know has entered the chain since
+    // their commitment, so it can be revealed
+    pub chain_ready: [bool; 4],
+    pub revealing: bool,
+    // commit-reveal: hash of the round id and the secrets revealed so far;
+    // hash chain: the chain itself, seeded by the VRF at start and extended by every roll
+    pub randomness_mix: [u8; 32],
     pub players: [Pubkey; 4],
-    pub winner: Pubkey, // ??? is there one winner or multiple???
+    // who may join the lobby
+    pub access: LobbyAccess,
+    // seats taken in the lobby, creator included
+    pub joined_count: u8,
+    // unix timestamp each seat was taken, 0 for free seats
+    pub joined_at: [i64; 4],
+    // finishing place of each color (1 is the winner), 0 while still playing
+    pub places: [u8; 4],
+    pub rules: RuleSet,
+    pub vault_bump: u8,
+    // SPL mint the game is staked in, native SOL when unset
+    pub mint: Option<Pubkey>,
+    // lamports (or base units of `mint`) every player deposits, 0 for free games
+    pub entry_fee: u64,
+    // share of the pot in basis points for 1st, 2nd and 3rd place
+    pub payouts: [u16; 3],
+    // protocol fee in basis points, fixed from `Config` when the game is created
+    pub protocol_fee_bps: u16,
+    // the vault has been paid out (always true for free games)
+    pub settled: bool,
+}
+
+/// Everything a game is set up with, whether a player creates it or a queue matches it.
+#[derive(Clone, Copy)]
+pub struct GameSettings {
+    pub num_players: u8,
+    pub rules: RuleSet,
+    pub mint: Option<Pubkey>,
+    pub entry_fee: u64,
+    pub payouts: [u16; 3],
+    pub protocol_fee_bps: u16,
+    pub turn_timeout: i64,
+    pub max_missed_turns: u8,
+    pub randomness: RandomnessMode,
+    pub rated: bool,
+    pub access: LobbyAccess,
+}
+
+impl GameSettings {
+    pub fn validate(&self) -> Result<()> {
+        let num_players = self.num_players;
+        require!(
+            num_players == 2 || num_players == 3 || num_players == 4,
+            LudoError::InvalidNumPlayers
+        );
+        // a longer turn would outlast the game, which may be closed once abandoned
+        require!(
+            self.turn_timeout > 0 && self.turn_timeout <= ABANDONED_GAME_TIMEOUT,
+            LudoError::InvalidTurnTimeout
+        );
+        require!(
+            Rules::from(self.rules).is_valid(),
+            LudoError::InvalidRuleSet
+        );
+        require!(
+            !self.rules.teams || num_players == 4,
+            LudoError::TeamsNeedFourPlayers
+        );
+        if self.entry_fee > 0 {
+            // the first SOL deposit has to cover the rent of the vault
+            require!(
+                self.mint.is_some() || self.entry_fee >= Rent::get()?.minimum_balance(0),
+                LudoError::EntryFeeTooLow
+            );
+            require!(
+                self.payouts.iter().sum::<u16>() == BPS_DENOMINATOR
+                    && self.payouts[num_players as usize - 1..]
+                        .iter()
+                        .all(|&bps| bps == 0),
+                LudoError::InvalidPayouts
+            );
+        }
+        Ok(())
+    }
 }
 
 impl Game {
-    pub fn next_player(&mut self) {
-        loop {
-            self.cur_player = (self.cur_player + 1) % 4;
-            if self.cur_player_key() != Pubkey::default() {
-                break;
-            }
+    /// A lobby nobody is seated in yet.
+    pub fn new(
+        seed: u64,
+        bump: u8,
+        vault_bump: u8,
+        creator: Pubkey,
+        settings: GameSettings,
+    ) -> Result<Self> {
+        Self::new_at(
+            Clock::get()?.unix_timestamp,
+            seed,
+            bump,
+            vault_bump,
+            creator,
+            settings,
+        )
+    }
+
+    /// `new` with the clock read by the caller.
+    pub(crate) fn new_at(
+        now: i64,
+        seed: u64,
+        bump: u8,
+        vault_bump: u8,
+        creator: Pubkey,
+        settings: GameSettings,
+    ) -> Result<Self> {
+        Ok(Self {
+            seed,
+            bump,
+            creator,
+            last_activity: now,
+            turn_timeout: settings.turn_timeout,
+            turn_deadline: now
+                .checked_add(settings.turn_timeout)
+                .ok_or(LudoError::InvalidTurnTimeout)?,
+            max_missed_turns: settings.max_missed_turns,
+            missed_turns: [0; 4],
+            num_players: settings.num_players,
+            cur_player: 0,
+            token_positions: [[-1; 4]; 4],
+            game_state: GameState::NotStarted,
+            current_roll: 0,
+            rolls: 0,
+            sixes: [0; 4],
+            captures: [0; 4],
+            captured: [0; 4],
+            stats_recorded: false,
+            rated: settings.rated,
+            six_count: 0,
+            randomness: settings.randomness,
+            vrf_nonce: 0,
+            pending_request: None,
+            commitments: [[0; 32]; 4],
+            chain_ready: [false; 4],
+            revealing: false,
+            randomness_mix: [0; 32],
+            players: [Pubkey::default(); 4],
+            access: settings.access,
+            joined_count: 0,
+            joined_at: [0; 4],
+            places: [0; 4],
+            rules: settings.rules,
+            vault_bump,
+            mint: settings.mint,
+            entry_fee: settings.entry_fee,
+            payouts: settings.payouts,
+            protocol_fee_bps: settings.protocol_fee_bps,
+            settled: settings.entry_fee == 0,
+        })
+    }
+
+    pub fn board(&self) -> BoardState {
+        BoardState {
+            seated: self.players.map(|player| player != Pubkey::default()),
+            token_positions: self.token_positions,
+            places: self.places,
+            cur_player: self.cur_player,
+            six_count: self.six_count,
+            rules: self.rules.into(),
+        }
+    }
+
+    pub fn set_board(&mut self, board: &BoardState) {
+        self.token_positions = board.token_positions;
+        self.places = board.places;
+        self.cur_player = board.cur_player;
+        self.six_count = board.six_count;
+    }
+
+    /// Records activity and restarts the turn clock.
+    pub fn touch(&mut self) -> Result<()> {
+        self.last_activity = Clock::get()?.unix_timestamp;
+        self.turn_deadline = self
+            .last_activity
+            .checked_add(self.turn_timeout)
+            .ok_or(LudoError::InvalidTurnTimeout)?;
+        Ok(())
+    }
+
+    /// Seats `player` on `color` in the lobby.
+    pub fn seat(&mut self, color: u8, player: Pubkey) -> Result<()> {
+        require!(
+            !self.players.contains(&player),
+            LudoError::PlayerAlreadyJoined
+        );
+        require!(
+            self.players[color as usize] == Pubkey::default(),
+            LudoError::ColorAlreadyTaken
+        );
+        self.players[color as usize] = player;
+        self.joined_at[color as usize] = Clock::get()?.unix_timestamp;
+        self.joined_count += 1;
+        Ok(())
+    }
+
+    /// Frees the lobby seat of `color`.
+    pub fn unseat(&mut self, color: u8) {
+        self.players[color as usize] = Pubkey::default();
+        self.joined_at[color as usize] = 0;
+        self.joined_count -= 1;
+    }
+
+    fn next_request_id(&mut self) -> Pubkey {
+        self.vrf_nonce += 1;
+        let id = hashv(&[&self.seed.to_le_bytes(), &self.vrf_nonce.to_le_bytes()]);
+        Pubkey::new_from_array(id.to_bytes())
+    }
+
+    /// Starts a randomness request and returns its id, invalidating any pending one.
+    pub fn request_randomness(&mut self) -> Pubkey {
+        let id = self.next_request_id();
+        self.pending_request = Some(id);
+        id
+    }
+
+    /// A hash-chain player holding a ready commitment has to reveal it. Taking the VRF or
+    /// committing anew instead would let them pick between a roll they can already compute
+    /// and a fresh one.
+    pub fn chain_commitment_binds(&self, color: u8) -> bool {
+        self.randomness == RandomnessMode::HashChain
+            && self.commitments[color as usize] != [0; 32]
+            && self.chain_ready[color as usize]
+    }
+
+    /// Checks that the current player may roll with the VRF: always in VRF games, and in
+    /// hash-chain games as a fallback while they have no ready commitment.
+    pub fn check_vrf_roll(&self) -> Result<()> {
+        require!(
+            self.randomness != RandomnessMode::CommitReveal,
+            LudoError::WrongRandomnessMode
+        );
+        require!(
+            !self.chain_commitment_binds(self.cur_player),
+            LudoError::CommitmentReady
+        );
+        Ok(())
+    }
+
+    /// Opens a commit-reveal round unless one is already under way.
+    pub fn open_round(&mut self) -> Result<()> {
+        let board = self.board();
+        if self.revealing
+            || (0..4)
+                .any(|color| board.is_active(color) && self.commitments[color as usize] != [0; 32])
+        {
+            return Ok(());
+        }
+        self.commitments = [[0; 32]; 4];
+        self.randomness_mix = self.next_request_id().to_bytes();
+        self.touch()
+    }
+
+    pub fn reset_round(&mut self) {
+        self.commitments = [[0; 32]; 4];
+        self.revealing = false;
+    }
+
+    /// Colors the commit-reveal round is waiting on: a commitment, or a reveal once `revealing`.
+    pub fn pending_players(&self) -> [bool; 4] {
+        let board = self.board();
+        core::array::from_fn(|color| {
+            board.is_active(color as u8) && (self.commitments[color] == [0; 32]) != self.revealing
+        })
+    }
+
+    /// Hands the dice to the seat `rnd` places after the first one taken.
+    pub fn start(&mut self, rnd: u8) -> Result<()> {
+        if self.cur_player_key() == Pubkey::default() {
+            self.next_player();
+        }
+
+        for _ in 0..rnd {
+            self.next_player();
         }
+
+        self.game_state = GameState::RollDice;
+        self.touch()
+    }
+
+    /// Puts a fresh roll in play, passing the dice on straight away when it leaves no
+    /// legal move. Returns whether the turn was skipped.
+    pub fn land_roll(&mut self, roll: u8) -> Result<bool> {
+        self.rolls += 1;
+        if roll == 6 {
+            self.sixes[self.cur_player as usize] += 1;
+        }
+        let mut board = self.board();
+        let skipped = !ludo_engine::has_legal_move(&board, roll);
+        if skipped {
+            ludo_engine::skip_turn(&mut board);
+            self.set_board(&board);
+            self.game_state = GameState::RollDice;
+        } else {
+            self.current_roll = roll;
+            self.game_state = GameState::Move;
+        }
+        self.touch()?;
+        Ok(skipped)
+    }
+
+    pub fn is_abandoned(&self) -> Result<bool> {
+        Ok(self.game_state != GameState::Finished
+            && Clock::get()?.unix_timestamp > self.last_activity + ABANDONED_GAME_TIMEOUT)
+    }
+
+    pub fn next_player(&mut self) {
+        let mut board = self.board();
+        board.next_player();
+        self.set_board(&board);
     }
 
     #[inline(always)]
     pub fn cur_player_key(&self) -> Pubkey {
         self.players[self.cur_player as usize]
     }
+
+    pub fn color_of(&self, player: &Pubkey) -> Option<u8> {
+        self.players
+            .iter()
+            .position(|key| key == player)
+            .map(|color| color as u8)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, InitSpace)]
+pub enum LobbyAccess {
+    Public,
+    // joiners need this key to co-sign, its keypair being handed out as the invite. A
+    // signature only covers its own transaction, so seeing a join does not let anyone in.
+    Invite(Pubkey),
+    // the only wallet that may take each color, default for a seat open to anyone
+    AllowList([Pubkey; 4]),
+}
+
+impl LobbyAccess {
+    /// Checks that `player`, co-signed by `invite` if any, may take `color`.
+    pub fn check(&self, color: u8, player: &Pubkey, invite: Option<&Pubkey>) -> Result<()> {
+        match self {
+            LobbyAccess::Public => {}
+            LobbyAccess::Invite(key) => {
+                let invite = invite.ok_or(LudoError::InviteRequired)?;
+                require!(invite == key, LudoError::WrongInvite);
+            }
+            LobbyAccess::AllowList(allowed) => {
+                let allowed = allowed[color as usize];
+                require!(
+                    allowed == Pubkey::default() || allowed == *player,
+                    LudoError::NotInvited
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, InitSpace)]
+pub enum RandomnessMode {
+    // MagicBlock VRF oracle
+    Vrf,
+    // every active player commits a hashed secret, the roll comes from all the reveals
+    CommitReveal,
+    // the VRF only picks the first player and seeds a chain every roll is derived from
+    HashChain,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, InitSpace)]
@@ -40,3 +425,112 @@ pub enum GameState {
     Move,
     Finished,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn settings(num_players: u8, randomness: RandomnessMode) -> GameSettings {
+        GameSettings {
+            num_players,
+            rules: RuleSet::default(),
+            mint: None,
+            entry_fee: 0,
+            payouts: [BPS_DENOMINATOR, 0, 0],
+            protocol_fee_bps: 0,
+            turn_timeout: 60,
+            max_missed_turns: 0,
+            randomness,
+            rated: false,
+            access: LobbyAccess::Public,
+        }
+    }
+
+    /// A started game with every player seated from red on, red to roll.
+    pub(crate) fn started_game(settings: GameSettings) -> Game {
+        let mut game = Game::new_at(0, 1, 255, 254, Pubkey::new_unique(), settings).unwrap();
+        for color in 0..settings.num_players as usize {
+            game.players[color] = Pubkey::new_unique();
+        }
+        game.joined_count = settings.num_players;
+        game.game_state = GameState::RollDice;
+        game
+    }
+
+    #[test]
+    fn ready_chain_commitment_rules_out_the_vrf() {
+        let mut game = started_game(settings(2, RandomnessMode::HashChain));
+        // no commitment yet, or one made since the last randomness the player did not know
+        assert!(game.check_vrf_roll().is_ok());
+        game.commitments[0] = [1; 32];
+        assert!(game.check_vrf_roll().is_ok());
+
+        game.chain_ready[0] = true;
+        assert!(game.chain_commitment_binds(0));
+        assert_eq!(
+            game.check_vrf_roll(),
+            Err(LudoError::CommitmentReady.into())
+        );
+
+        // only the holder is bound
+        game.cur_player = 1;
+        game.chain_ready[1] = true;
+        assert!(game.check_vrf_roll().is_ok());
+
+        let game = started_game(settings(2, RandomnessMode::Vrf));
+        assert!(game.check_vrf_roll().is_ok());
+        let game = started_game(settings(2, RandomnessMode::CommitReveal));
+        assert_eq!(
+            game.check_vrf_roll(),
+            Err(LudoError::WrongRandomnessMode.into())
+        );
+    }
+
+    #[test]
+    fn public_lobby_lets_anyone_in() {
+        let player = Pubkey::new_unique();
+        assert!(LobbyAccess::Public.check(1, &player, None).is_ok());
+    }
+
+    #[test]
+    fn invite_has_to_co_sign() {
+        let invite = Pubkey::new_unique();
+        let access = LobbyAccess::Invite(invite);
+        let player = Pubkey::new_unique();
+
+        assert!(access.check(1, &player, Some(&invite)).is_ok());
+        assert_eq!(
+            access.check(1, &player, None),
+            Err(LudoError::InviteRequired.into())
+        );
+        assert_eq!(
+            access.check(1, &player, Some(&Pubkey::new_unique())),
+            Err(LudoError::WrongInvite.into())
+        );
+    }
+
+    #[test]
+    fn allow_list_binds_listed_colors_only() {
+        let invited = Pubkey::new_unique();
+        let access = LobbyAccess::AllowList([
+            Pubkey::new_unique(),
+            invited,
+            Pubkey::default(),
+            Pubkey::default(),
+        ]);
+
+        assert!(access.check(1, &invited, None).is_ok());
+        assert!(access.check(2, &invited, None).is_ok());
+        assert!(access.check(3, &Pubkey::new_unique(), None).is_ok());
+        assert_eq!(
+            access.check(0, &invited, None),
+            Err(LudoError::NotInvited.into())
+        );
+        // an invite signature does not stand in for the listed wallet
+        let stranger = Pubkey::new_unique();
+        assert_eq!(
+            access.check(1, &stranger, Some(&stranger)),
+            Err(LudoError::NotInvited.into())
+        );
+    }
+}
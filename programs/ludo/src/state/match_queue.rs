@@ -0,0 +1,189 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    GameSettings, LobbyAccess, RandomnessMode, RuleSet, BPS_DENOMINATOR, QUEUE_MAX_MISSED_TURNS,
+    QUEUE_TURN_TIMEOUT, RATING_WINDOW, RATING_WINDOW_PERIOD, RATING_WINDOW_STEP,
+};
+
+pub const MAX_QUEUE_LEN: usize = 16;
+
+/// Players waiting for a game of `num_players` staking `entry_fee` lamports under `rules`.
+/// Their stakes stay on the queue account until they are matched or leave.
+#[account]
+#[derive(InitSpace)]
+pub struct MatchQueue {
+    pub bump: u8,
+    pub num_players: u8,
+    pub entry_fee: u64,
+    pub rules: RuleSet,
+    // tables are made of players rated close to each other instead of first come first served
+    pub bracketed: bool,
+    // oldest first
+    #[max_len(MAX_QUEUE_LEN)]
+    pub entries: Vec<QueueEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct QueueEntry {
+    pub player: Pubkey,
+    // rating when the player queued
+    pub rating: u16,
+    pub enqueued_at: i64,
+}
+
+impl MatchQueue {
+    /// Settings of the games the queue creates: winner takes all, rated, VRF dice.
+    pub fn settings(&self, protocol_fee_bps: u16) -> GameSettings {
+        GameSettings {
+            num_players: self.num_players,
+            rules: self.rules,
+            mint: None,
+            entry_fee: self.entry_fee,
+            payouts: [BPS_DENOMINATOR, 0, 0],
+            protocol_fee_bps,
+            turn_timeout: QUEUE_TURN_TIMEOUT,
+            max_missed_turns: QUEUE_MAX_MISSED_TURNS,
+            randomness: RandomnessMode::Vrf,
+            rated: true,
+            access: LobbyAccess::Public,
+        }
+    }
+
+    pub fn position(&self, player: &Pubkey) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.player == *player)
+    }
+
+    /// Entries the next table is made of, oldest first, `None` while none can be filled.
+    /// Plain queues take the oldest players. Bracketed queues seat the oldest player that
+    /// enough others fit in with: every pair at the table is rated within that player's
+    /// window, which widens the longer they wait.
+    pub fn next_table(&self, now: i64) -> Option<Vec<usize>> {
+        let num_players = self.num_players as usize;
+        if !self.bracketed {
+            return (self.entries.len() >= num_players).then(|| (0..num_players).collect());
+        }
+        (0..self.entries.len()).find_map(|anchor| {
+            let window = rating_window(now - self.entries[anchor].enqueued_at);
+            let mut table = vec![anchor];
+            for i in 0..self.entries.len() {
+                if table.len() == num_players {
+                    break;
+                }
+                let rating = self.entries[i].rating;
+                if i != anchor
+                    && table
+                        .iter()
+                        .all(|&j| self.entries[j].rating.abs_diff(rating) <= window)
+                {
+                    table.push(i);
+                }
+            }
+            table.sort_unstable();
+            (table.len() == num_players).then_some(table)
+        })
+    }
+}
+
+pub fn rating_window(waited: i64) -> u16 {
+    let periods = (waited.max(0) / RATING_WINDOW_PERIOD).min(u16::MAX as i64) as u16;
+    RATING_WINDOW.saturating_add(periods.saturating_mul(RATING_WINDOW_STEP))
+}
+
+/// Colors handed out to a matched table in queue order, two players sitting opposite.
+pub fn seat_colors(num_players: u8) -> &'static [u8] {
+    match num_players {
+        2 => &[0, 2],
+        3 => &[0, 1, 2],
+        _ => &[0, 1, 2, 3],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bracketed_queue(num_players: u8, entries: &[(u16, i64)]) -> MatchQueue {
+        MatchQueue {
+            bump: 0,
+            num_players,
+            entry_fee: 0,
+            rules: RuleSet::default(),
+            bracketed: true,
+            entries: entries
+                .iter()
+                .map(|&(rating, enqueued_at)| QueueEntry {
+                    player: Pubkey::new_unique(),
+                    rating,
+                    enqueued_at,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn rating_window_widens_every_period() {
+        assert_eq!(rating_window(-5), RATING_WINDOW);
+        assert_eq!(rating_window(0), RATING_WINDOW);
+        assert_eq!(rating_window(RATING_WINDOW_PERIOD - 1), RATING_WINDOW);
+        assert_eq!(
+            rating_window(RATING_WINDOW_PERIOD),
+            RATING_WINDOW + RATING_WINDOW_STEP
+        );
+        assert_eq!(
+            rating_window(5 * RATING_WINDOW_PERIOD + 1),
+            RATING_WINDOW + 5 * RATING_WINDOW_STEP
+        );
+        assert_eq!(rating_window(i64::MAX), u16::MAX);
+    }
+
+    #[test]
+    fn plain_queue_takes_the_oldest() {
+        let mut queue = bracketed_queue(2, &[(1000, 0), (2000, 0), (1500, 0)]);
+        queue.bracketed = false;
+        assert_eq!(queue.next_table(0), Some(vec![0, 1]));
+        queue.entries.truncate(1);
+        assert_eq!(queue.next_table(0), None);
+    }
+
+    #[test]
+    fn bracketed_queue_waits_for_close_ratings() {
+        let queue = bracketed_queue(2, &[(1000, 0), (1200, 0)]);
+        assert_eq!(queue.next_table(0), None);
+        // after two periods the window reaches 200
+        assert_eq!(queue.next_table(2 * RATING_WINDOW_PERIOD), Some(vec![0, 1]));
+    }
+
+    #[test]
+    fn bracketed_queue_skips_to_a_later_anchor() {
+        let queue = bracketed_queue(2, &[(1000, 0), (1500, 10), (1550, 20)]);
+        assert_eq!(queue.next_table(20), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn table_spread_stays_within_the_window() {
+        // 1100 has everyone within its window, but 1000 and 1200 are two windows apart
+        let queue = bracketed_queue(3, &[(1000, 0), (1100, 0), (1200, 0)]);
+        assert_eq!(queue.next_table(0), None);
+
+        let queue = bracketed_queue(3, &[(1000, 0), (1200, 0), (1010, 0), (1100, 0)]);
+        let table = queue.next_table(0).unwrap();
+        assert_eq!(table, vec![0, 2, 3]);
+        for &i in &table {
+            for &j in &table {
+                let spread = queue.entries[i].rating.abs_diff(queue.entries[j].rating);
+                assert!(spread <= RATING_WINDOW);
+            }
+        }
+    }
+
+    #[test]
+    fn table_includes_the_anchor() {
+        // only the oldest player's widened window takes in the newcomers
+        let now = 2 * RATING_WINDOW_PERIOD;
+        let queue = bracketed_queue(3, &[(1000, 0), (1150, now), (1180, now), (860, now)]);
+        assert_eq!(queue.next_table(now), Some(vec![0, 1, 2]));
+        assert_eq!(queue.next_table(0), None);
+    }
+}
@@ -1,3 +1,11 @@
+pub mod config;
 pub mod game;
+pub mod match_queue;
+pub mod player_profile;
+pub mod rule_set;
 
+pub use config::*;
 pub use game::*;
+pub use match_queue::*;
+pub use player_profile::*;
+pub use rule_set::*;
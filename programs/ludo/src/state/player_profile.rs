@@ -0,0 +1,107 @@
+use anchor_lang::prelude::*;
+
+use crate::{rating_changes, Game, GameState, LudoError, INITIAL_RATING};
+
+/// Lifetime statistics of a wallet, created the first time it creates or joins a game.
+#[account]
+#[derive(InitSpace)]
+pub struct PlayerProfile {
+    pub bump: u8,
+    pub player: Pubkey,
+    pub games_played: u32,
+    // first places, or first and second for the winning side of a teams game
+    pub wins: u32,
+    // games finished in each place, 1st to 4th
+    pub placements: [u32; 4],
+    pub captures_made: u32,
+    pub captures_suffered: u32,
+    pub sixes_rolled: u32,
+    // rolls of all games played, for the average game length
+    pub total_game_rolls: u64,
+    pub rating: u16,
+    pub rated_games: u32,
+}
+
+impl PlayerProfile {
+    /// Sets up a profile `init_if_needed` has just created.
+    pub fn open(&mut self, player: Pubkey, bump: u8) {
+        if self.player == Pubkey::default() {
+            self.player = player;
+            self.bump = bump;
+            self.rating = INITIAL_RATING;
+        }
+    }
+
+    pub fn average_game_length(&self) -> u64 {
+        self.total_game_rolls
+            .checked_div(self.games_played as u64)
+            .unwrap_or(0)
+    }
+
+    fn record(&mut self, game: &Game, color: usize) -> Result<()> {
+        let place = game.places[color];
+        // every seated color has a place once the game is finished
+        let place_index = place.checked_sub(1).ok_or(LudoError::GameNotFinished)? as usize;
+        self.games_played += 1;
+        if place == 1 || (game.rules.teams && place == 2) {
+            self.wins += 1;
+        }
+        self.placements[place_index] += 1;
+        self.captures_made += game.captures[color] as u32;
+        self.captures_suffered += game.captured[color] as u32;
+        self.sixes_rolled += game.sixes[color] as u32;
+        self.total_game_rolls += game.rolls as u64;
+        Ok(())
+    }
+}
+
+/// Adds a finished game to the `profiles` of its players, passed in color order. Does
+/// nothing without profiles or when the game was already recorded. Returns whether it
+/// recorded the game. Profiles are never delegated, so a game played on the ephemeral
+/// rollup is recorded by `settle_game` once it is back on the base layer.
+pub fn record_stats(game: &mut Game, profiles: &[AccountInfo]) -> Result<bool> {
+    if profiles.is_empty() || game.stats_recorded {
+        return Ok(false);
+    }
+    require!(
+        game.game_state == GameState::Finished,
+        LudoError::GameNotFinished
+    );
+
+    let mut infos = profiles.iter();
+    let mut seated: [Option<(&AccountInfo, PlayerProfile)>; 4] = Default::default();
+    for (color, seat) in seated.iter_mut().enumerate() {
+        let player = game.players[color];
+        if player == Pubkey::default() {
+            continue;
+        }
+        let info = infos.next().ok_or(LudoError::InvalidProfile)?;
+        require_keys_eq!(*info.owner, crate::ID, LudoError::InvalidProfile);
+        let profile = PlayerProfile::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require_keys_eq!(profile.player, player, LudoError::InvalidProfile);
+        *seat = Some((info, profile));
+    }
+
+    let ratings = seated
+        .each_ref()
+        .map(|seat| seat.as_ref().map(|(_, profile)| profile.rating));
+    let changes = if game.rated {
+        rating_changes(ratings, game.places, game.rules.teams)
+    } else {
+        [0; 4]
+    };
+    for (color, seat) in seated.into_iter().enumerate() {
+        let Some((info, mut profile)) = seat else {
+            continue;
+        };
+        profile.record(game, color)?;
+        if game.rated {
+            profile.rating =
+                (profile.rating as i32 + changes[color]).clamp(0, u16::MAX as i32) as u16;
+            profile.rated_games += 1;
+        }
+        profile.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+    }
+    game.stats_recorded = true;
+    Ok(true)
+}
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use ludo_engine::Rules;
+
+/// On-chain copy of `ludo_engine::Rules`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, InitSpace)]
+pub struct RuleSet {
+    pub extra_turn_on_capture: bool,
+    pub extra_turn_on_home: bool,
+    pub release_rolls: u8,
+    pub max_consecutive_sixes: u8,
+    pub exact_finish: bool,
+    pub safe_squares: bool,
+    pub blockades: bool,
+    pub teams: bool,
+    pub partner_assist: bool,
+}
+
+impl RuleSet {
+    /// Hash identifying the rule set in PDA seeds.
+    pub fn key(&self) -> [u8; 32] {
+        let mut data = Vec::with_capacity(Self::INIT_SPACE);
+        self.serialize(&mut data).unwrap();
+        hash(&data).to_bytes()
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Rules::default().into()
+    }
+}
+
+impl From<RuleSet> for Rules {
+    fn from(rules: RuleSet) -> Self {
+        Self {
+            extra_turn_on_capture: rules.extra_turn_on_capture,
+            extra_turn_on_home: rules.extra_turn_on_home,
+            release_rolls: rules.release_rolls,
+            max_consecutive_sixes: rules.max_consecutive_sixes,
+            exact_finish: rules.exact_finish,
+            safe_squares: rules.safe_squares,
+            blockades: rules.blockades,
+            teams: rules.teams,
+            partner_assist: rules.partner_assist,
+        }
+    }
+}
+
+impl From<Rules> for RuleSet {
+    fn from(rules: Rules) -> Self {
+        Self {
+            extra_turn_on_capture: rules.extra_turn_on_capture,
+            extra_turn_on_home: rules.extra_turn_on_home,
+            release_rolls: rules.release_rolls,
+            max_consecutive_sixes: rules.max_consecutive_sixes,
+            exact_finish: rules.exact_finish,
+            safe_squares: rules.safe_squares,
+            blockades: rules.blockades,
+            teams: rules.teams,
+            partner_assist: rules.partner_assist,
+        }
+    }
+}